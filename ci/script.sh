@@ -10,6 +10,7 @@ main() {
 
     cross test --target $TARGET
     cross test --target $TARGET --release
+    cross test --target $TARGET --all-features
 }
 
 # we don't run the "test phase" when doing deploys
@@ -0,0 +1,94 @@
+//! Errors that can occur when running fcc.
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// A type alias for [`Result`]<T, [`Error`]>.
+///
+/// [`Result`]: std::result::Result
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The errors reported by fcc.
+#[derive(Debug)]
+pub enum Error {
+    /// Occurs if the path given at `index` does not point to a regular file.
+    InvalidPath { index: usize, path: PathBuf },
+
+    /// Occurs if an input argument is not a valid glob pattern.
+    Pattern(glob::PatternError),
+
+    /// Occurs if the sources contributed nothing to the output while that is not allowed.
+    EmptyOutput,
+
+    /// Represents an I/O error originating from a specific file.
+    File { path: PathBuf, source: io::Error },
+
+    /// Represents an error that originates from [`admerge`].
+    Merge(admerge::ErrorKind),
+}
+
+impl Error {
+    /// Wraps an I/O error that occurred while working on the given file.
+    pub fn file<P: Into<PathBuf>>(path: P, source: io::Error) -> Self {
+        Error::File {
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// Returns `true` if the error is the output refusing to go past `--limit-bytes`.
+    pub fn is_limit_reached(&self) -> bool {
+        match self {
+            Error::File { source, .. } | Error::Merge(admerge::ErrorKind::Io(source)) => {
+                crate::output::is_limit_reached(source)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidPath { index, path } => write!(
+                f,
+                "the path provided at index {} is not a valid file path: {}",
+                index,
+                path.display()
+            ),
+            Error::Pattern(e) => write!(f, "invalid glob pattern: {}", e),
+            Error::EmptyOutput => write!(f, "the sources contributed nothing to the output"),
+            Error::File { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Merge(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidPath { .. } | Error::EmptyOutput => None,
+            Error::Pattern(e) => Some(e),
+            Error::File { source, .. } => Some(source),
+            Error::Merge(e) => Some(e),
+        }
+    }
+}
+
+impl From<admerge::ErrorKind> for Error {
+    fn from(e: admerge::ErrorKind) -> Self {
+        Error::Merge(e)
+    }
+}
+
+impl From<glob::PatternError> for Error {
+    fn from(e: glob::PatternError) -> Self {
+        Error::Pattern(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Merge(admerge::ErrorKind::Io(e))
+    }
+}
@@ -0,0 +1,280 @@
+//! Collects the paths of the sources to be merged.
+use crate::error::{Error, Result};
+use tempfile::NamedTempFile;
+
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Parses newline-separated paths piped through `stdin`, keeping any spaces inside a path.
+///
+/// Lines are trimmed, and blank lines are ignored.
+pub fn parse_stdin_lines(buf: &str) -> Vec<PathBuf> {
+    buf.lines()
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Parses whitespace-separated paths piped through `stdin`, where a path containing spaces can be
+/// wrapped in single or double quotes.
+pub fn parse_stdin_words(buf: &str) -> Vec<PathBuf> {
+    split_words(buf).into_iter().map(PathBuf::from).collect()
+}
+
+// Splits on whitespace, keeping whitespace inside single or double quotes. Backslashes are
+// taken literally, so that Windows paths survive.
+fn split_words(buf: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+
+    for c in buf.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'') | (None, '"') => {
+                word.get_or_insert_with(String::new);
+                quote = Some(c);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+
+    words
+}
+
+/// Parses NUL-separated paths piped through `stdin` (as produced by `find -print0`), keeping any
+/// spaces and newlines inside a path.
+pub fn parse_stdin_nul(buf: &str) -> Vec<PathBuf> {
+    buf.split('\0')
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Splices the paths listed in every `@FILE` response file into the given paths, in place.
+///
+/// Response files list one path per line, as manifests do, and may reference further response
+/// files. A response file referencing itself, directly or not, is an error.
+pub fn expand_response_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut expanded = Vec::with_capacity(paths.len());
+    splice_response_files(paths, &mut Vec::new(), &mut expanded)?;
+    Ok(expanded)
+}
+
+// Splices response files into `expanded`, with `open` holding the response files being expanded.
+fn splice_response_files(
+    paths: &[PathBuf],
+    open: &mut Vec<PathBuf>,
+    expanded: &mut Vec<PathBuf>,
+) -> Result<()> {
+    for path in paths {
+        let response = match path.to_str().and_then(|p| p.strip_prefix('@')) {
+            Some(response) => Path::new(response),
+            None => {
+                expanded.push(path.clone());
+                continue;
+            }
+        };
+
+        let canonical = response
+            .canonicalize()
+            .map_err(|e| Error::file(response, e))?;
+        if open.contains(&canonical) {
+            let e = io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "response file references itself",
+            );
+            return Err(Error::file(response, e));
+        }
+
+        open.push(canonical);
+        splice_response_files(&read_manifest(response)?, open, expanded)?;
+        open.pop();
+    }
+
+    Ok(())
+}
+
+/// Expands every path containing `*`, `?` or `[` as a glob pattern, keeping other paths as is.
+///
+/// The matches of each pattern are sorted lexicographically. A pattern matching nothing is kept
+/// as a literal path.
+pub fn expand_globs(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut expanded = Vec::with_capacity(paths.len());
+    for path in paths {
+        let pattern = match path.to_str() {
+            Some(pattern) if pattern.contains(&['*', '?', '['][..]) => pattern,
+            _ => {
+                expanded.push(path.clone());
+                continue;
+            }
+        };
+
+        let mut matches = glob::glob(pattern)?
+            .map(|entry| entry.map_err(|e| Error::file(e.path().to_path_buf(), e.into())))
+            .collect::<Result<Vec<PathBuf>>>()?;
+        if matches.is_empty() {
+            matches.push(path.clone());
+        }
+        matches.sort();
+        expanded.extend(matches);
+    }
+
+    Ok(expanded)
+}
+
+/// Replaces every directory among the given paths with the regular files found underneath it,
+/// walked depth-first with the entries of each directory sorted by name.
+///
+/// Symlinked directories are only walked into if `follow_links` is set, in which case a link back
+/// to a directory being walked is skipped. Entries whose name starts with `.` are left out if
+/// `skip_hidden` is set. Other paths are kept as is.
+pub fn expand_dirs(
+    paths: &[PathBuf],
+    follow_links: bool,
+    skip_hidden: bool,
+) -> Result<Vec<PathBuf>> {
+    let mut expanded = Vec::with_capacity(paths.len());
+    for path in paths {
+        match path.is_dir() {
+            true => walk(
+                path,
+                follow_links,
+                skip_hidden,
+                &mut Vec::new(),
+                &mut expanded,
+            )?,
+            false => expanded.push(path.clone()),
+        }
+    }
+
+    Ok(expanded)
+}
+
+// Collects the regular files underneath the given directory into `files`, with `walking` holding
+// the canonical paths of the directories being walked.
+fn walk(
+    dir: &Path,
+    follow_links: bool,
+    skip_hidden: bool,
+    walking: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let canonical = fs::canonicalize(dir).map_err(|e| Error::file(dir, e))?;
+    if walking.contains(&canonical) {
+        return Ok(());
+    }
+    walking.push(canonical);
+
+    let mut entries = fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<io::Result<Vec<fs::DirEntry>>>())
+        .map_err(|e| Error::file(dir, e))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        if skip_hidden && entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let file_type = entry.file_type().map_err(|e| Error::file(&path, e))?;
+        if file_type.is_dir() || (follow_links && file_type.is_symlink() && path.is_dir()) {
+            walk(&path, follow_links, skip_hidden, walking, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+
+    walking.pop();
+    Ok(())
+}
+
+/// Copies `stdin` into a temporary file standing in for the `-` among the given paths, so that it
+/// can be seeked like any other source. The temporary file is removed once dropped.
+///
+/// Fails if `-` is given more than once, since `stdin` can only be read once.
+pub fn spool_stdin(paths: &mut [PathBuf]) -> Result<Option<NamedTempFile>> {
+    let mut dashes = paths.iter_mut().filter(|path| path.as_os_str() == "-");
+    let dash = match dashes.next() {
+        Some(dash) => dash,
+        None => return Ok(None),
+    };
+    if dashes.next().is_some() {
+        let e = io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "stdin (`-`) can only be given once",
+        );
+        return Err(e.into());
+    }
+
+    let mut spool = NamedTempFile::new()?;
+    io::copy(&mut io::stdin().lock(), &mut spool)?;
+    spool.flush()?;
+    *dash = spool.path().to_path_buf();
+    Ok(Some(spool))
+}
+
+/// Reads the paths listed in a manifest file, one path per line.
+///
+/// Lines are trimmed, and blank lines as well as lines starting with `#` are ignored.
+pub fn read_manifest<P: AsRef<Path>>(manifest: P) -> Result<Vec<PathBuf>> {
+    let manifest = manifest.as_ref();
+    let contents = fs::read_to_string(manifest).map_err(|e| Error::file(manifest, e))?;
+
+    Ok(contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Sorts the given paths in natural order, so that `part2` comes before `part10`.
+///
+/// The sort is stable, so paths that only differ in leading zeros keep their original order.
+pub fn sort_natural(paths: &mut [PathBuf]) {
+    paths.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+}
+
+// Compares two strings by comparing runs of digits numerically and everything else bytewise.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    loop {
+        match (a.first(), b.first()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x, rest_a) = split_digits(a);
+                let (y, rest_b) = split_digits(b);
+                // Leading zeros are ignored, so the longer run of significant digits is greater.
+                let (x, y) = (trim_zeros(x), trim_zeros(y));
+                match x.len().cmp(&y.len()).then_with(|| x.cmp(y)) {
+                    Ordering::Equal => (a, b) = (rest_a, rest_b),
+                    ordering => return ordering,
+                }
+            }
+            (Some(x), Some(y)) => match x.cmp(y) {
+                Ordering::Equal => (a, b) = (&a[1..], &b[1..]),
+                ordering => return ordering,
+            },
+        }
+    }
+}
+
+// Splits the leading run of ASCII digits off the given bytes.
+fn split_digits(s: &[u8]) -> (&[u8], &[u8]) {
+    let n = s.iter().take_while(|c| c.is_ascii_digit()).count();
+    s.split_at(n)
+}
+
+// Strips the leading zeros off a run of digits.
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    let n = digits.iter().take_while(|&&c| c == b'0').count();
+    &digits[n..]
+}
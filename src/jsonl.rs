@@ -0,0 +1,67 @@
+//! Validates JSON lines sources ahead of merging.
+use crate::error::{Error, Result};
+
+use tempfile::NamedTempFile;
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// What to do with a line that is not a valid JSON value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Drops the line.
+    DropInvalid,
+    /// Fails with the path and the line number of the line.
+    FailFast,
+}
+
+/// Checks that every non-blank line of the given paths is a valid JSON value.
+///
+/// With [`Mode::DropInvalid`], `dropped` is called with each invalid line as an error, and every
+/// path holding invalid lines is replaced with a temporary file holding its valid lines only,
+/// which is removed once the returned handles are dropped.
+pub fn validate<F>(paths: &mut [PathBuf], mode: Mode, mut dropped: F) -> Result<Vec<NamedTempFile>>
+where
+    F: FnMut(Error),
+{
+    let mut spools = Vec::new();
+    for path in paths.iter_mut() {
+        let bytes = fs::read(&path).map_err(|e| Error::file(&*path, e))?;
+        let mut valid = Vec::with_capacity(bytes.len());
+        let mut spooled = false;
+        for (n, line) in bytes.split_inclusive(|&b| b == b'\n').enumerate() {
+            let e = match line.iter().all(u8::is_ascii_whitespace) {
+                true => None,
+                false => serde_json::from_slice::<serde_json::Value>(line).err(),
+            };
+            let e = match e {
+                Some(e) => io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: invalid JSON: {}", n + 1, e),
+                ),
+                None => {
+                    valid.extend_from_slice(line);
+                    continue;
+                }
+            };
+            match mode {
+                Mode::FailFast => return Err(Error::file(&*path, e)),
+                Mode::DropInvalid => {
+                    dropped(Error::file(&*path, e));
+                    spooled = true;
+                }
+            }
+        }
+
+        if spooled {
+            let mut spool = NamedTempFile::new()?;
+            spool.write_all(&valid)?;
+            spool.flush()?;
+            *path = spool.path().to_path_buf();
+            spools.push(spool);
+        }
+    }
+
+    Ok(spools)
+}
@@ -0,0 +1,112 @@
+//! Line-oriented layouts that rearrange the lines of the given sources instead of stacking them.
+use crate::error::{Error, Result};
+use admerge::ErrorKind;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+// Opens every given path as a buffered line reader.
+fn open_all(paths: &[PathBuf]) -> Result<Vec<BufReader<File>>> {
+    paths
+        .iter()
+        .map(|p| {
+            File::open(p)
+                .map(BufReader::new)
+                .map_err(|e| Error::file(p, e))
+        })
+        .collect()
+}
+
+/// Writes `n` lines from each source in turn, cycling until every source is exhausted.
+///
+/// Sources running out of lines are dropped from the rotation. A line that lacks a trailing
+/// newline (the last line of a source) is terminated with `newline` so it does not run into the
+/// next block.
+pub fn interleave<W: Write>(
+    paths: &[PathBuf],
+    n: usize,
+    newline: &[u8],
+    writer: &mut W,
+) -> Result<()> {
+    if paths.is_empty() {
+        return Err(ErrorKind::NothingPassed.into());
+    }
+
+    let mut readers = open_all(paths)?;
+    let mut line = Vec::new();
+
+    while !readers.is_empty() {
+        let mut i = 0;
+        while i < readers.len() {
+            let mut exhausted = false;
+            for _ in 0..n {
+                line.clear();
+                if readers[i].read_until(b'\n', &mut line)? == 0 {
+                    exhausted = true;
+                    break;
+                }
+                writer.write_all(&line)?;
+                if !line.ends_with(b"\n") {
+                    writer.write_all(newline)?;
+                }
+            }
+
+            if exhausted {
+                readers.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Joins the sources side by side: each output row is the n-th line of every source, separated
+/// by `delim` and terminated by `newline` (like `paste -d`).
+///
+/// Sources with fewer lines contribute empty fields once they run out.
+pub fn columns<W: Write>(
+    paths: &[PathBuf],
+    delim: &[u8],
+    newline: &[u8],
+    writer: &mut W,
+) -> Result<()> {
+    if paths.is_empty() {
+        return Err(ErrorKind::NothingPassed.into());
+    }
+
+    let mut readers = open_all(paths)?;
+    let mut fields = vec![Vec::new(); readers.len()];
+
+    loop {
+        let mut exhausted = true;
+        for (reader, field) in readers.iter_mut().zip(fields.iter_mut()) {
+            field.clear();
+            if reader.read_until(b'\n', field)? > 0 {
+                exhausted = false;
+            }
+            if field.ends_with(b"\n") {
+                field.pop();
+                if field.ends_with(b"\r") {
+                    field.pop();
+                }
+            }
+        }
+        if exhausted {
+            break;
+        }
+
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                writer.write_all(delim)?;
+            }
+            writer.write_all(field)?;
+        }
+        writer.write_all(newline)?;
+    }
+
+    Ok(())
+}
@@ -1,10 +1,34 @@
-use admerge::{FileMerger, Newline, Pad, Skip};
+use admerge::{Newline, Pad, Skip};
 use structopt::StructOpt;
 
-use std::fs::OpenOptions;
-use std::io::prelude::*;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+#[cfg(feature = "progress")]
+use std::io::IsTerminal;
+use std::io::{self, prelude::*, BufWriter};
 use std::path::PathBuf;
 
+mod error;
+mod input;
+#[cfg(feature = "json")]
+mod jsonl;
+mod layout;
+mod merge;
+mod output;
+#[cfg(feature = "progress")]
+mod progress;
+mod source;
+#[cfg(feature = "encoding")]
+mod transcode;
+
+use error::Error;
+use merge::Merger;
+use output::{
+    AtomicFile, BlankSqueezer, DelimiterNormalizer, Interruptible, LineNumberer, NumberFormat, Tee,
+    TrailingNewlineTrimmer, WhitespaceMarker,
+};
+use source::Source;
+
 macro_rules! stderr {
     ($($arg:tt)*) => {
         use std::io::Write;
@@ -19,10 +43,19 @@ macro_rules! stderr {
     about = "reads files from <STDIN> and merges their contents into <STDOUT>.",
     after_help = "NOTES:
 
-    1. When reading from <STDIN>, file paths must be space-separated or newline-separated."
+    1. When reading from <STDIN>, file paths are newline-separated, unless `--paths-space`
+       (quote paths containing spaces) or `--paths-null` says otherwise.
+
+    2. Inputs containing `*`, `?` or `[` are expanded as glob patterns, whose matches are merged
+       in lexicographic order. Inputs starting with `@` name response files listing one path per
+       line, which are spliced in place. A `-` input stands for <STDIN>, and can be given once.
+
+    3. `--sort` defaults to lexicographic order. Sorting is stable, and applies to all inputs once
+       collected, before `--reverse`.",
+    group = structopt::clap::ArgGroup::with_name("pad")
 )]
 struct Opts {
-    /// Sets the input files, reads from <STDIN> if not present
+    /// Sets the input files, glob patterns or `@FILE` response files, reads from <STDIN> if neither this nor `--files-from` is present
     #[structopt(
         long,
         short,
@@ -40,6 +73,47 @@ struct Opts {
         parse(from_os_str)
     )]
     output: Option<PathBuf>,
+    /// Appends to the <FILE> given by `--output` instead of replacing it
+    #[structopt(long, short = "a", display_order = 2, requires = "output")]
+    append: bool,
+    /// Copies the output written to <FILE> to <STDOUT> as well
+    #[structopt(long, display_order = 2, requires = "output")]
+    tee: bool,
+    /// Reads input paths from a manifest <FILE>, one per line, after those given by `--input`
+    #[structopt(long, display_order = 1, value_name = "FILE", parse(from_os_str))]
+    files_from: Option<PathBuf>,
+    /// Reads NUL-separated paths from <STDIN>, as produced by `find -print0`
+    #[structopt(long, short = "0", visible_alias = "paths-null", display_order = 1)]
+    null: bool,
+    /// Reads newline-separated paths from <STDIN>, one path per line, which is the default
+    #[structopt(long, display_order = 1, conflicts_with_all = &["null", "paths-space"])]
+    paths_newline: bool,
+    /// Reads whitespace-separated paths from <STDIN>, quoting paths containing spaces
+    #[structopt(long, display_order = 1, conflicts_with = "null")]
+    paths_space: bool,
+    /// Sorts the inputs by path, either lexicographically or in natural order (`part2` before `part10`)
+    #[structopt(
+        long,
+        display_order = 2,
+        value_name = "ORDER",
+        possible_values = &["lexical", "natural"]
+    )]
+    sort: Option<Option<String>>,
+    /// Merges the inputs in reverse order, after any sorting
+    #[structopt(long, display_order = 2)]
+    reverse: bool,
+    /// Merges the regular files underneath any input directory, walked in sorted order
+    #[structopt(long, short = "r", display_order = 2)]
+    recursive: bool,
+    /// Walks into symlinked directories when merging directories recursively
+    #[structopt(long, display_order = 2, requires = "recursive")]
+    follow_links: bool,
+    /// Leaves out files and directories whose name starts with `.` when merging directories recursively
+    #[structopt(long, display_order = 2, requires = "recursive")]
+    skip_hidden: bool,
+    /// Merges the whole list of inputs a number of times in a row, writing nothing for 0
+    #[structopt(long, display_order = 2, value_name = "NUMBER")]
+    repeat: Option<usize>,
     /// Skips a number of lines from the head of each source
     #[structopt(
         long,
@@ -93,8 +167,32 @@ struct Opts {
         )]
     skip_mode: String,
     /// Fills some padding between each source
-    #[structopt(long, short = "p", display_order = 10, value_name = "STRING")]
+    #[structopt(
+        long,
+        short = "p",
+        display_order = 10,
+        value_name = "STRING",
+        group = "pad"
+    )]
     padding: Option<String>,
+    /// Fills the contents of the given file as padding, instead of a string
+    #[structopt(
+        long,
+        display_order = 10,
+        value_name = "FILE",
+        parse(from_os_str),
+        group = "pad"
+    )]
+    pad_file: Option<PathBuf>,
+    /// Only pads between two sources if their contents differ
+    #[structopt(long, display_order = 11, requires = "pad")]
+    pad_distinct: bool,
+    /// Writes the given string once at the start of the output, ahead of any padding
+    #[structopt(long, display_order = 10, value_name = "STRING")]
+    start_with: Option<String>,
+    /// Writes the given string once at the end of the output, after any padding
+    #[structopt(long, display_order = 10, value_name = "STRING")]
+    end_with: Option<String>,
     /// Controls where paddings should be inserted into.
     #[structopt(
             long,
@@ -105,9 +203,28 @@ struct Opts {
             possible_values = &["beforestart", "afterend", "between", "all"],
         )]
     pad_mode: String,
+    /// Drops the lines of each source that are empty or only made of whitespace, after any skips
+    #[structopt(long, display_order = 10, conflicts_with_all = &["interleave", "columns"])]
+    skip_blank_lines: bool,
+    /// Leaves out empty sources entirely, along with the paddings around them
+    #[structopt(long, display_order = 10)]
+    skip_empty: bool,
     /// Appends a newline after each source if source is not already ended with newline
     #[structopt(long, short = "n", display_order = 12)]
     newline: bool,
+    /// Removes the newline at the very end of the output, leaving newlines between sources intact
+    #[structopt(long, display_order = 12, conflicts_with = "newline")]
+    trim_trailing_newline: bool,
+    /// Appends the missing newline of each source in the style it already uses, falling back to `--newline-style`
+    #[structopt(long, display_order = 13, requires = "newline")]
+    detect_newline_style: bool,
+    /// Writes exactly one newline between two sources, whether or not they end with one
+    #[structopt(
+        long,
+        display_order = 12,
+        conflicts_with_all = &["pad", "interleave", "columns"]
+    )]
+    newline_between: bool,
     /// The style of newline, either unix-style `LF` or dos-style `CRLF`
     #[structopt(
             long,
@@ -118,6 +235,149 @@ struct Opts {
             possible_values = &["lf", "crlf"],
         )]
     newline_style: String,
+    /// Interleaves blocks of a number of lines from each source in turn, instead of stacking sources
+    #[structopt(
+        long,
+        display_order = 14,
+        value_name = "NUMBER",
+        validator = is_positive,
+        conflicts_with_all = &[
+            "skip-head", "skip-tail", "skip-head-once", "skip-tail-once",
+            "headonce", "tailonce", "padding", "pad-file",
+        ]
+    )]
+    interleave: Option<usize>,
+    /// Joins sources side by side, separating the lines of each row with the given delimiter
+    #[structopt(
+        long,
+        display_order = 15,
+        value_name = "DELIM",
+        conflicts_with_all = &[
+            "skip-head", "skip-tail", "skip-head-once", "skip-tail-once",
+            "headonce", "tailonce", "padding", "pad-file", "interleave",
+        ]
+    )]
+    columns: Option<String>,
+    /// Stops cleanly once the given number of bytes has been written to the output
+    #[structopt(long, display_order = 16, value_name = "NUMBER")]
+    limit_bytes: Option<u64>,
+    /// Flushes and closes the output cleanly on Ctrl-C, reporting how many bytes were written
+    #[structopt(long, display_order = 16)]
+    flush_on_interrupt: bool,
+    /// Rewrites every unquoted <FROM> delimiter into <TO>, e.g. to merge `;` and `,` separated CSVs
+    #[structopt(
+        long,
+        display_order = 17,
+        number_of_values = 2,
+        value_names = &["FROM", "TO"],
+        validator = is_single_byte
+    )]
+    normalize_delimiter: Option<Vec<String>>,
+    /// Drops leading lines starting with the given prefix from each source, before any other skips
+    #[structopt(
+        long,
+        display_order = 18,
+        value_name = "PREFIX",
+        validator = is_not_empty,
+        conflicts_with_all = &["interleave", "columns"]
+    )]
+    skip_prefix: Option<String>,
+    /// Skips the head line of each source only if it equals the head line of the first source
+    #[structopt(
+        long,
+        display_order = 19,
+        conflicts_with_all = &["skip-head", "skip-head-once", "headonce", "interleave", "columns"]
+    )]
+    dedup_headers: bool,
+    /// Writes a UTF-8 byte order mark at the very start of the output, dropping those of the sources (not when appending to a non-empty file)
+    #[structopt(long, display_order = 20, conflicts_with_all = &["interleave", "columns"])]
+    bom: bool,
+    /// Drops a leading UTF-8 byte order mark from each source, before any other skips
+    #[structopt(long, display_order = 20, conflicts_with_all = &["interleave", "columns"])]
+    strip_bom: bool,
+    /// Transcodes sources starting with a UTF-16 byte order mark into UTF-8, before any skips
+    #[cfg(feature = "encoding")]
+    #[structopt(long, display_order = 20)]
+    transcode_utf8: bool,
+    /// Checks that each non-blank line of the sources is a JSON value, then drops invalid lines with a warning or fails on the first one
+    #[cfg(feature = "json")]
+    #[structopt(
+        long,
+        display_order = 20,
+        value_name = "MODE",
+        possible_values = &["pass", "drop-invalid", "fail-fast"]
+    )]
+    jsonl: Option<String>,
+    /// Numbers the lines of the output like `cat -n`, continuously across sources
+    #[structopt(long, display_order = 20)]
+    number: bool,
+    /// Formats line numbers with a `{}` placeholder, like `{:04}: `, instead of `cat -n` style
+    #[structopt(long, display_order = 20, value_name = "FORMAT", requires = "number")]
+    number_format: Option<NumberFormat>,
+    /// Collapses runs of empty lines into a single one like `cat -s`, across sources as well
+    #[structopt(long, display_order = 20)]
+    squeeze_blank: bool,
+    /// Writes a `$` at the end of each output line and renders carriage returns as `^M`, like `cat -E`
+    #[structopt(long, display_order = 20)]
+    show_ends: bool,
+    /// Renders tabs in the output as `^I` like `cat -T`
+    #[structopt(long, display_order = 20)]
+    show_tabs: bool,
+    /// Prints the number of lines and bytes the output would have to <STDOUT>, like `wc -lc`, instead of the output
+    #[structopt(long, display_order = 20, conflicts_with_all = &["output", "dry-run"])]
+    count_only: bool,
+    /// Reports what each source would contribute to the output, without writing anything
+    #[structopt(long, display_order = 20, conflicts_with_all = &["interleave", "columns"])]
+    dry_run: bool,
+    /// Fails if nothing gets written to the output, e.g. because skips drop every line
+    #[structopt(long, display_order = 21, conflicts_with = "dry-run")]
+    error_on_empty: bool,
+    /// Skips inputs that are not readable files with a warning, instead of failing
+    #[structopt(long, display_order = 21)]
+    keep_going: bool,
+    /// Lets a source that cannot be skipped as requested contribute nothing, instead of failing
+    #[structopt(long, display_order = 21)]
+    lenient_skip: bool,
+    /// Logs each source to <STDERR> as it gets merged, along with what was skipped if given twice
+    #[structopt(
+        long,
+        short = "v",
+        display_order = 22,
+        parse(from_occurrences),
+        conflicts_with_all = &["interleave", "columns"]
+    )]
+    verbose: u8,
+    /// Renders a progress bar on <STDERR>, unless writing to a piped <STDOUT> without `always`
+    #[cfg(feature = "progress")]
+    #[structopt(
+        long,
+        display_order = 23,
+        value_name = "WHEN",
+        possible_values = &["auto", "always"],
+        conflicts_with_all = &["verbose", "dry-run"]
+    )]
+    progress: Option<Option<String>>,
+}
+
+fn is_positive(v: String) -> Result<(), String> {
+    match v.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(()),
+        _ => Err(String::from("expected a positive integer")),
+    }
+}
+
+fn is_single_byte(v: String) -> Result<(), String> {
+    match v.len() {
+        1 => Ok(()),
+        _ => Err(String::from("expected a single-byte delimiter")),
+    }
+}
+
+fn is_not_empty(v: String) -> Result<(), String> {
+    match v.is_empty() {
+        false => Ok(()),
+        true => Err(String::from("expected a non-empty value")),
+    }
 }
 
 fn main() {
@@ -125,39 +385,476 @@ fn main() {
 
     if let Err(e) = run(&opts) {
         stderr!("fcc: {}", e);
+        if output::interrupted() {
+            std::process::exit(130);
+        }
         std::process::exit(1);
     }
 }
 
-fn run(opts: &Opts) -> admerge::Result<()> {
-    // Reads input from cli argument (primary) or `stdin` (fallback).
-    let input = match &opts.input {
-        Some(paths) => paths.clone(),
-        None => {
-            let mut buf = String::new();
-            std::io::stdin().lock().read_to_string(&mut buf)?;
-
-            let mut paths = buf
-                .split('\n')
-                .filter(|v| v != &"")
-                .map(|v| v.trim())
-                .map(PathBuf::from)
-                .collect::<Vec<PathBuf>>();
-
-            if paths.len() <= 1 {
-                paths = buf
-                    .split(' ')
-                    .filter(|v| v != &"")
-                    .map(|v| v.trim())
-                    .map(PathBuf::from)
-                    .collect::<Vec<PathBuf>>();
+fn run(opts: &Opts) -> error::Result<()> {
+    match opts.flush_on_interrupt {
+        true => output::catch_interrupt(),
+        false if opts.output.is_some() && !opts.append => output::clean_up_on_interrupt(),
+        false => Ok(()),
+    }
+    .map_err(io::Error::other)?;
+
+    let mut input = collect_input(opts)?;
+    if opts.recursive {
+        input = input::expand_dirs(&input, opts.follow_links, opts.skip_hidden)?;
+    }
+    match opts.sort.as_ref().map(|order| order.as_deref()) {
+        Some(Some("natural")) => input::sort_natural(&mut input),
+        Some(_) => input.sort(),
+        None => (),
+    }
+    if opts.reverse {
+        input.reverse();
+    }
+    // Inputs replaced by temporary files are reported under the names they were given as.
+    let mut names = HashMap::new();
+    let before = input.clone();
+    let _stdin = match opts.input.is_some() || opts.files_from.is_some() {
+        true => input::spool_stdin(&mut input)?,
+        false => None,
+    };
+    rename_spooled(&mut names, &before, &input);
+
+    // Fails fast on invalid paths, before the output gets created.
+    match opts.keep_going {
+        true => source::retain_valid(&mut input, |e| {
+            stderr!("fcc: skipping: {}", e);
+        }),
+        false => source::validate(&input)?,
+    }
+    if opts.skip_empty {
+        source::retain_non_empty(&mut input)?;
+    }
+    // Sources are reported with the size they had before being transcoded or filtered.
+    let mut sizes = file_sizes(&input)?;
+    #[cfg(feature = "encoding")]
+    let _transcoded = {
+        let before = input.clone();
+        let spools = match opts.transcode_utf8 {
+            true => transcode::spool_utf8(&mut input)?,
+            false => Vec::new(),
+        };
+        rename_spooled(&mut names, &before, &input);
+        spools
+    };
+    #[cfg(feature = "json")]
+    let _validated = {
+        let before = input.clone();
+        let spools = match opts.jsonl.as_deref() {
+            Some("drop-invalid") => jsonl::validate(&mut input, jsonl::Mode::DropInvalid, |e| {
+                stderr!("fcc: dropping: {}", renamed(&names, e));
+            })?,
+            Some("fail-fast") => jsonl::validate(&mut input, jsonl::Mode::FailFast, |_| ())
+                .map_err(|e| renamed(&names, e))?,
+            _ => Vec::new(),
+        };
+        rename_spooled(&mut names, &before, &input);
+        spools
+    };
+    // Repeats the inputs once they are checked and spooled, so that each one is only handled once.
+    if let Some(n) = opts.repeat {
+        let len = input.len().checked_mul(n).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "too many inputs to repeat")
+        })?;
+        input = input.iter().cycle().take(len).cloned().collect();
+        sizes = sizes.iter().cycle().take(len).copied().collect();
+    }
+    let names = input
+        .iter()
+        .map(|path| names.get(path).unwrap_or(path).clone())
+        .collect::<Vec<PathBuf>>();
+
+    // The padding file is read once, before merging.
+    let pad_file = match &opts.pad_file {
+        Some(path) => Some(fs::read(path).map_err(|e| Error::file(path, e))?),
+        None => None,
+    };
+    let padding = match &opts.padding {
+        Some(padding) => Some(padding.as_bytes()),
+        None => pad_file.as_deref(),
+    };
+
+    if opts.dry_run {
+        return dry_run(opts, padding, &input, &names);
+    }
+
+    // Writes result to file (primary) or `stdout` (fallback).
+    // Appending cannot be done atomically, so a failing merge keeps what has been appended.
+    let stdout = std::io::stdout();
+    let (mut file, mut appended) = match &opts.output {
+        Some(path) if opts.append => {
+            let opened = OpenOptions::new().append(true).create(true).open(path);
+            (None, Some(opened.map_err(|e| Error::file(path, e))?))
+        }
+        Some(path) => {
+            let created = AtomicFile::create(path);
+            (Some(created.map_err(|e| Error::file(path, e))?), None)
+        }
+        None => (None, None),
+    };
+    let mut counter = output::Counter::new(io::sink());
+    let writer = match (file.as_mut(), appended.as_mut()) {
+        _ if opts.count_only => output(opts, &mut counter),
+        (Some(file), _) => output(opts, file),
+        (_, Some(file)) => output(opts, file),
+        (None, None) => output(opts, stdout.lock()),
+    };
+    let mut sink = Interruptible::new(writer);
+    if let Some(limit) = opts.limit_bytes {
+        sink.limit(limit);
+    }
+    // The byte order mark and `--start-with` go straight to the output, so that no transform
+    // applies to them.
+    let prefix = match writes_bom(opts) {
+        true => sink.write_all(&source::BOM),
+        false => Ok(()),
+    };
+    let prefix = prefix.and_then(|()| match &opts.start_with {
+        Some(start) => sink.write_all(start.as_bytes()),
+        None => Ok(()),
+    });
+    let prefixed = sink.written();
+
+    let mut writer = transform(opts, Box::new(&mut sink));
+    #[cfg(feature = "progress")]
+    let bar = match &opts.progress {
+        Some(when) if when.as_deref() == Some("always") || shows_progress(opts) => {
+            let bar = progress::bar(&input)?;
+            writer = Box::new(bar.wrap_write(writer));
+            Some(bar)
+        }
+        _ => None,
+    };
+
+    let result = prefix.map_err(Error::from).and_then(|()| {
+        match (opts.interleave, &opts.columns) {
+            _ if opts.repeat == Some(0) => (),
+            (Some(n), _) => layout::interleave(&input, n, newline(opts), &mut writer)?,
+            (_, Some(delim)) => {
+                layout::columns(&input, delim.as_bytes(), newline(opts), &mut writer)?
             }
+            _ => merge(opts, padding, &input, &names, &sizes, &mut writer)?,
+        }
+        Ok(())
+    });
+    let result = result.and_then(|()| Ok(writer.flush()?));
+    drop(writer);
+    let merged = sink.written() - prefixed;
+    // Likewise, `--end-with` is written once every transform has been flushed.
+    let result = result.and_then(|()| match &opts.end_with {
+        Some(end) => Ok(sink.write_all(end.as_bytes())?),
+        None => Ok(()),
+    });
+    // Stopping at the limit is a success, whatever the merge was in the middle of. A merge writing
+    // nothing is only a failure when asked for, whatever gets written around it.
+    let result = match result {
+        Err(e) if e.is_limit_reached() => Ok(()),
+        Ok(()) if opts.error_on_empty && merged == 0 => Err(Error::EmptyOutput),
+        result => result,
+    };
+    #[cfg(feature = "progress")]
+    if let Some(bar) = bar {
+        bar.finish_and_clear();
+    }
 
-            paths
+    // Keeps what has been written so far on interruption, but nothing on any other error.
+    let interrupted = output::interrupted();
+    if result.is_ok() || interrupted {
+        sink.flush()?;
+        let written = sink.written();
+        drop(sink);
+        if let (Some(file), Some(path)) = (file, &opts.output) {
+            file.commit().map_err(|e| Error::file(path, e))?;
+        }
+        if interrupted {
+            return Err(
+                io::Error::other(format!("interrupted after writing {} bytes", written)).into(),
+            );
         }
+        if opts.count_only {
+            writeln!(stdout.lock(), "{} {}", counter.newlines(), counter.bytes())?;
+        }
+    }
+
+    result
+}
+
+// Buffers the output written to the given destination, so that paddings and appended newlines
+// don't each cost a syscall, copying it to `stdout` as well with `--tee`.
+fn output<'a, W: Write + 'a>(opts: &Opts, dest: W) -> Box<dyn Write + 'a> {
+    let dest = BufWriter::new(dest);
+    match opts.tee {
+        true => Box::new(Tee::new(dest, BufWriter::new(io::stdout().lock()))),
+        false => Box::new(dest),
+    }
+}
+
+// Returns `true` if a byte order mark goes at the start of the output, which is not the case when
+// appending to a file that already has contents.
+fn writes_bom(opts: &Opts) -> bool {
+    match &opts.output {
+        Some(path) if opts.append => opts.bom && path.metadata().map_or(true, |m| m.len() == 0),
+        _ => opts.bom,
+    }
+}
+
+// Stacks the output transforms requested by the given options on top of the given writer.
+fn transform<'a>(opts: &Opts, mut writer: Box<dyn Write + 'a>) -> Box<dyn Write + 'a> {
+    if opts.number {
+        let format = opts.number_format.clone().unwrap_or_default();
+        writer = Box::new(LineNumberer::new(writer, format));
+    }
+    if opts.show_ends || opts.show_tabs {
+        writer = Box::new(WhitespaceMarker::new(
+            writer,
+            opts.show_ends,
+            opts.show_tabs,
+        ));
+    }
+    if opts.squeeze_blank {
+        writer = Box::new(BlankSqueezer::new(writer));
+    }
+    if let Some(delims) = &opts.normalize_delimiter {
+        let (from, to) = (delims[0].as_bytes()[0], delims[1].as_bytes()[0]);
+        writer = Box::new(DelimiterNormalizer::new(writer, from, to));
+    }
+    if opts.trim_trailing_newline {
+        writer = Box::new(TrailingNewlineTrimmer::new(writer));
+    }
+    writer
+}
+
+// Returns `true` if a progress bar would not get in the way of the output.
+#[cfg(feature = "progress")]
+fn shows_progress(opts: &Opts) -> bool {
+    opts.output.is_some() || io::stdout().is_terminal()
+}
+
+// Collects input paths from cli arguments (primary) or `stdin` (fallback).
+fn collect_input(opts: &Opts) -> error::Result<Vec<PathBuf>> {
+    if opts.input.is_none() && opts.files_from.is_none() {
+        let mut buf = String::new();
+        io::stdin().lock().read_to_string(&mut buf)?;
+        return Ok(match (opts.null, opts.paths_newline, opts.paths_space) {
+            (true, _, _) => input::parse_stdin_nul(&buf),
+            (_, false, true) => input::parse_stdin_words(&buf),
+            _ => input::parse_stdin_lines(&buf),
+        });
+    }
+
+    let paths = input::expand_response_files(opts.input.as_deref().unwrap_or_default())?;
+    let mut paths = input::expand_globs(&paths)?;
+    if let Some(manifest) = &opts.files_from {
+        paths.extend(input::read_manifest(manifest)?);
+    }
+
+    Ok(paths)
+}
+
+// Merges the given paths into the given writer with the given padding, reporting them under the
+// given names and sizes.
+fn merge<W: Write>(
+    opts: &Opts,
+    padding: Option<&[u8]>,
+    paths: &[PathBuf],
+    names: &[PathBuf],
+    sizes: &[u64],
+    writer: &mut W,
+) -> error::Result<()> {
+    let sources = open_sources(opts, paths)?;
+    if opts.verbose == 0 {
+        merger(opts, padding).merge_sources_into(sources, writer)?;
+        return Ok(());
+    }
+
+    let spooled = file_sizes(paths)?;
+    let windows = sources.iter().map(Source::len).collect::<Vec<u64>>();
+    merger(opts, padding).merge_with(sources, writer, |i, c| {
+        stderr!(
+            "fcc: {}: {} bytes, {} bytes written",
+            names[i].display(),
+            sizes[i],
+            c.bytes
+        );
+        if opts.verbose > 1 {
+            let appended = match c.newline {
+                Some(Newline::Lf) => 1,
+                Some(Newline::Crlf) => 2,
+                None => 0,
+            };
+            stderr!(
+                "fcc: {}: {} bytes dropped up front, {} bytes skipped{}",
+                names[i].display(),
+                spooled[i] - windows[i],
+                windows[i] - (c.bytes - appended),
+                if c.newline.is_some() {
+                    ", newline appended"
+                } else {
+                    ""
+                }
+            );
+        }
+    })?;
+    Ok(())
+}
+
+// Returns the size of each of the given files.
+fn file_sizes(paths: &[PathBuf]) -> error::Result<Vec<u64>> {
+    paths
+        .iter()
+        .map(|path| {
+            path.metadata()
+                .map(|m| m.len())
+                .map_err(|e| Error::file(path, e))
+        })
+        .collect()
+}
+
+// Prints the contribution of each source and the total output length to `stdout`.
+fn dry_run(
+    opts: &Opts,
+    padding: Option<&[u8]>,
+    paths: &[PathBuf],
+    names: &[PathBuf],
+) -> error::Result<()> {
+    let sources = open_sources(opts, paths)?;
+    let mut contributions = Vec::with_capacity(sources.len());
+    // The total is counted through the same limit and transforms as the output would be.
+    let mut total = Interruptible::new(io::sink());
+    if let Some(limit) = opts.limit_bytes {
+        total.limit(limit);
+    }
+    let result = (|| -> error::Result<()> {
+        if writes_bom(opts) {
+            total.write_all(&source::BOM)?;
+        }
+        if let Some(start) = &opts.start_with {
+            total.write_all(start.as_bytes())?;
+        }
+        let mut writer = transform(opts, Box::new(&mut total));
+        if opts.repeat != Some(0) {
+            merger(opts, padding).merge_with(sources, &mut writer, |_, c| contributions.push(c))?;
+        }
+        writer.flush()?;
+        drop(writer);
+        if let Some(end) = &opts.end_with {
+            total.write_all(end.as_bytes())?;
+        }
+        Ok(())
+    })();
+    match result {
+        Err(e) if e.is_limit_reached() => (),
+        result => result?,
+    }
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    for (path, c) in names.iter().zip(contributions) {
+        write!(
+            stdout,
+            "{}: {} bytes, {} lines",
+            path.display(),
+            c.bytes,
+            c.lines
+        )?;
+        if c.newline.is_some() {
+            write!(stdout, ", newline appended")?;
+        }
+        writeln!(stdout)?;
+    }
+    writeln!(stdout, "total: {} bytes", total.written())?;
+
+    Ok(())
+}
+
+// Records the name each path replaced between `before` and `after` was given as, following the
+// paths that have been replaced more than once back to their original name.
+fn rename_spooled(names: &mut HashMap<PathBuf, PathBuf>, before: &[PathBuf], after: &[PathBuf]) {
+    for (before, after) in before.iter().zip(after) {
+        if before != after {
+            let name = names.get(before).unwrap_or(before).clone();
+            names.insert(after.clone(), name);
+        }
+    }
+}
+
+// Reports a file error under the name the file was given as.
+#[cfg(feature = "json")]
+fn renamed(names: &HashMap<PathBuf, PathBuf>, e: Error) -> Error {
+    match e {
+        Error::File { path, source } => match names.get(&path) {
+            Some(name) => Error::file(name, source),
+            None => Error::File { path, source },
+        },
+        e => e,
+    }
+}
+
+// Returns the newline bytes of the configured newline style.
+fn newline(opts: &Opts) -> &'static [u8] {
+    match opts.newline_style.as_str() {
+        "crlf" => b"\r\n",
+        _ => b"\n",
+    }
+}
+
+// Opens the given paths as sources, narrowed according to the given options.
+fn open_sources(opts: &Opts, paths: &[PathBuf]) -> error::Result<Vec<Source<File>>> {
+    let mut sources = paths
+        .iter()
+        .map(|path| {
+            let open = || {
+                let mut source = Source::new(File::open(path)?)?;
+                if opts.bom || opts.strip_bom {
+                    source.skip_bom()?;
+                }
+                if let Some(prefix) = &opts.skip_prefix {
+                    source.skip_lines_starting_with(prefix.as_bytes())?;
+                }
+                Ok(source)
+            };
+            open().map_err(|e| Error::file(path, e))
+        })
+        .collect::<error::Result<Vec<Source<File>>>>()?;
+
+    if opts.dedup_headers {
+        dedup_headers(paths, &mut sources)?;
+    }
+
+    Ok(sources)
+}
+
+// Drops the head line of each source after the first one, if it equals the head line of the first.
+fn dedup_headers(paths: &[PathBuf], sources: &mut [Source<File>]) -> error::Result<()> {
+    let (first, rest) = match sources.split_first_mut() {
+        Some(split) => split,
+        None => return Ok(()),
     };
+    let header = match first.head_line().map_err(|e| Error::file(&paths[0], e))? {
+        Some(header) => header,
+        None => return Ok(()),
+    };
+    for (source, path) in rest.iter_mut().zip(&paths[1..]) {
+        source
+            .skip_head_line_if(&header)
+            .map_err(|e| Error::file(path, e))?;
+    }
+    Ok(())
+}
 
-    let mut merger = FileMerger::new();
+// Builds a `Merger` according to the given options, padding sources with the given padding.
+fn merger<'a>(opts: &'a Opts, padding: Option<&'a [u8]>) -> Merger<'a> {
+    let mut merger = Merger::new();
+    merger.lenient_skip(opts.lenient_skip);
+    merger.detect_newline(opts.detect_newline_style);
+    merger.skip_blank_lines(opts.skip_blank_lines);
+    merger.pad_distinct(opts.pad_distinct);
     match opts.skip_mode.as_str() {
         "lines" => {
             if let Some(n) = opts.skip_head {
@@ -208,22 +905,18 @@ fn run(opts: &Opts) -> admerge::Result<()> {
         (_, other) => panic!("unexpected `{}` in newline-style", other),
     }
 
-    match (&opts.padding, opts.pad_mode.as_str()) {
+    match (padding, opts.pad_mode.as_str()) {
         (Some(padding), "beforestart") => {
-            merger.pad_with(Pad::Before(padding.as_bytes()));
+            merger.pad_with(Pad::Before(padding));
         }
         (Some(padding), "afterend") => {
-            merger.pad_with(Pad::After(padding.as_bytes()));
+            merger.pad_with(Pad::After(padding));
         }
         (Some(padding), "between") => {
-            merger.pad_with(Pad::Between(padding.as_bytes()));
+            merger.pad_with(Pad::Between(padding));
         }
         (Some(padding), "all") => {
-            merger.pad_with(Pad::Custom(
-                Some(padding.as_bytes()),
-                Some(padding.as_bytes()),
-                Some(padding.as_bytes()),
-            ));
+            merger.pad_with(Pad::Custom(Some(padding), Some(padding), Some(padding)));
         }
         (None, "beforestart") => (),
         (None, "afterend") => (),
@@ -232,16 +925,67 @@ fn run(opts: &Opts) -> admerge::Result<()> {
         (_, other) => panic!("unexpected `{}` in pad-mode", other),
     }
 
-    // Writes result to file (primary) or `stdout` (fallback).
-    match &opts.output {
-        Some(path) => {
-            let mut file = OpenOptions::new().create(true).write(true).open(path)?;
-            merger.with_paths(input, &mut file)?;
+    if opts.newline_between {
+        merger.newline_between(match opts.newline_style.as_str() {
+            "crlf" => Newline::Crlf,
+            _ => Newline::Lf,
+        });
+    }
+
+    merger
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::NamedTempFile;
+
+    // A writer counting the calls to `write` it gets.
+    #[derive(Default)]
+    struct WriteCounter {
+        calls: usize,
+        bytes: Vec<u8>,
+    }
+
+    impl Write for WriteCounter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.calls += 1;
+            self.bytes.extend_from_slice(buf);
+            Ok(buf.len())
         }
-        None => {
-            merger.with_paths(input, &mut std::io::stdout().lock())?;
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
         }
-    };
+    }
 
-    Ok(())
+    #[test]
+    fn buffered_output_batches_small_writes() {
+        let files = (0..20)
+            .map(|i| {
+                let mut file = NamedTempFile::new().unwrap();
+                write!(file, "{}", i).unwrap();
+                file
+            })
+            .collect::<Vec<NamedTempFile>>();
+        let paths = files
+            .iter()
+            .map(|file| file.path().to_path_buf())
+            .collect::<Vec<PathBuf>>();
+        let opts = Opts::from_iter(["fcc", "-n"]);
+
+        let mut unbuffered = WriteCounter::default();
+        merge(&opts, Some(b"=="), &paths, &paths, &[], &mut unbuffered).unwrap();
+
+        let mut buffered = WriteCounter::default();
+        let mut writer = output(&opts, &mut buffered);
+        merge(&opts, Some(b"=="), &paths, &paths, &[], &mut writer).unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        assert_eq!(buffered.bytes, unbuffered.bytes);
+        assert!(unbuffered.calls >= 3 * paths.len() - 1);
+        assert_eq!(buffered.calls, 1);
+    }
 }
@@ -0,0 +1,272 @@
+//! Merges sources one at a time on top of admerge, so that each source can be observed.
+use admerge::{ErrorKind, Newline, Pad, Result, RsMerger, Skip};
+
+use crate::output::{Counter, LineFilter};
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::io::{self, prelude::*, SeekFrom};
+
+/// What a single source contributed to the output, paddings excluded.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Contribution {
+    /// The number of bytes written.
+    pub bytes: u64,
+    /// The number of lines written, counting an unterminated last line.
+    pub lines: u64,
+    /// The style of the ending newline appended to the source, if any.
+    pub newline: Option<Newline>,
+}
+
+/// A merger with the same options as [`RsMerger`], that hands each source to admerge on its own.
+///
+/// "Once" skips are resolved per source: the first source keeps its head and the last source
+/// keeps its tail. Paddings are written by this merger, strictly before the first source, between
+/// two sources and after the last source.
+#[derive(Debug, Clone, Default)]
+pub struct Merger<'a> {
+    skip_head: Option<Skip<'a>>,
+    skip_tail: Option<Skip<'a>>,
+    pad_before: Option<&'a [u8]>,
+    pad_between: Option<&'a [u8]>,
+    pad_after: Option<&'a [u8]>,
+    newline: Option<Newline>,
+    newline_between: Option<Newline>,
+    detect_newline: bool,
+    skip_blank_lines: bool,
+    pad_distinct: bool,
+    lenient: bool,
+}
+
+impl<'a> Merger<'a> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn skip_head(&mut self, skip: Skip<'a>) -> &mut Self {
+        self.skip_head = Some(skip);
+        self
+    }
+
+    pub fn skip_tail(&mut self, skip: Skip<'a>) -> &mut Self {
+        self.skip_tail = Some(skip);
+        self
+    }
+
+    pub fn pad_with(&mut self, padding: Pad<'a>) -> &mut Self {
+        let (before, between, after) = match padding {
+            Pad::Before(padding) => (Some(padding), None, None),
+            Pad::Between(padding) => (None, Some(padding), None),
+            Pad::After(padding) => (None, None, Some(padding)),
+            Pad::Custom(before, between, after) => (before, between, after),
+            _ => (None, None, None),
+        };
+        self.pad_before = before;
+        self.pad_between = between;
+        self.pad_after = after;
+        self
+    }
+
+    /// Only writes the padding between two sources if their contents differ.
+    pub fn pad_distinct(&mut self, yes: bool) -> &mut Self {
+        self.pad_distinct = yes;
+        self
+    }
+
+    pub fn force_ending_newline(&mut self, newline: Newline) -> &mut Self {
+        self.newline = Some(newline);
+        self
+    }
+
+    /// Writes exactly one newline in the given style between two sources, whether or not they end
+    /// with a newline already, and none after the last source.
+    pub fn newline_between(&mut self, newline: Newline) -> &mut Self {
+        self.newline_between = Some(newline);
+        self
+    }
+
+    /// Appends the ending newline in the style a source predominantly uses already, falling back
+    /// to the style given to [`force_ending_newline`] for sources without any newline.
+    ///
+    /// [`force_ending_newline`]: Merger::force_ending_newline
+    pub fn detect_newline(&mut self, yes: bool) -> &mut Self {
+        self.detect_newline = yes;
+        self
+    }
+
+    /// Drops the lines of each source that are empty or only made of whitespace, after skips.
+    pub fn skip_blank_lines(&mut self, yes: bool) -> &mut Self {
+        self.skip_blank_lines = yes;
+        self
+    }
+
+    /// Lets a source that cannot be skipped as configured (e.g. skipping more lines than it has)
+    /// contribute nothing, instead of failing the whole merge.
+    pub fn lenient_skip(&mut self, yes: bool) -> &mut Self {
+        self.lenient = yes;
+        self
+    }
+
+    /// Merges the given sources into the given writer.
+    pub fn merge_sources_into<RS, W>(&self, sources: Vec<RS>, writer: &mut W) -> Result<()>
+    where
+        RS: Read + Seek,
+        W: Write,
+    {
+        self.merge_with(sources, writer, |_, _| ())
+    }
+
+    /// Merges the given sources into the given writer, calling `inspect` with the index and the
+    /// contribution of each source right after it has been written.
+    pub fn merge_with<RS, W, F>(
+        &self,
+        sources: Vec<RS>,
+        writer: &mut W,
+        mut inspect: F,
+    ) -> Result<()>
+    where
+        RS: Read + Seek,
+        W: Write,
+        F: FnMut(usize, Contribution),
+    {
+        let len = sources.len();
+        if len == 0 {
+            return Err(ErrorKind::NothingPassed);
+        }
+
+        if let Some(padding) = self.pad_before {
+            writer.write_all(padding)?;
+        }
+
+        let mut previous = None;
+        for (i, mut source) in sources.into_iter().enumerate() {
+            let digest = match self.pad_distinct {
+                true => Some(digest(&mut source)?),
+                false => None,
+            };
+            if let (true, Some(newline)) = (i > 0, self.newline_between) {
+                writer.write_all(newline_bytes(newline))?;
+            }
+            if i > 0 && (digest.is_none() || digest != previous) {
+                if let Some(padding) = self.pad_between {
+                    writer.write_all(padding)?;
+                }
+            }
+            previous = digest;
+
+            let style = match self.newline {
+                Some(fallback) if self.detect_newline => {
+                    Some(detect_newline(&mut source)?.unwrap_or(fallback))
+                }
+                style => style,
+            };
+            let mut counter = Counter::new(&mut *writer);
+            let merger = self.merger_for(i, len);
+            let merged = match self.skip_blank_lines {
+                true => {
+                    let mut filter = LineFilter::new(&mut counter, |line: &[u8]| {
+                        !line.iter().all(u8::is_ascii_whitespace)
+                    });
+                    merger
+                        .merge_sources_into(vec![source], &mut filter)
+                        .and_then(|()| Ok(filter.finish()?))
+                }
+                false => merger.merge_sources_into(vec![source], &mut counter),
+            };
+            let contribution = match merged {
+                Ok(()) => {
+                    // Decides on what has actually been written, as skips may have cut off or
+                    // uncovered the ending newline of the source. A source that wrote nothing
+                    // gets no newline either.
+                    let newline = match (style, counter.last()) {
+                        (Some(style), Some(last)) if last != b'\n' => Some(style),
+                        _ => None,
+                    };
+                    if let Some(newline) = newline {
+                        counter.write_all(newline_bytes(newline))?;
+                    }
+                    Contribution {
+                        bytes: counter.bytes(),
+                        lines: counter.lines(),
+                        newline,
+                    }
+                }
+                // Skips are resolved before anything of the source is written.
+                Err(ErrorKind::InvalidSkip) if self.lenient => Contribution::default(),
+                Err(e) => return Err(e),
+            };
+            inspect(i, contribution);
+        }
+
+        if let Some(padding) = self.pad_after {
+            writer.write_all(padding)?;
+        }
+
+        Ok(())
+    }
+
+    // Builds the admerge merger used for the source at index `i` out of `len` sources.
+    fn merger_for(&self, i: usize, len: usize) -> RsMerger<'a> {
+        let mut merger = RsMerger::new();
+        if let Some(skip) = resolve(&self.skip_head, i == 0) {
+            merger.skip_head(skip);
+        }
+        if let Some(skip) = resolve(&self.skip_tail, i + 1 == len) {
+            merger.skip_tail(skip);
+        }
+        merger
+    }
+}
+
+// Turns a "once" skip into a plain skip, or into no skip at all for the exempted source.
+fn resolve<'a>(skip: &Option<Skip<'a>>, exempted: bool) -> Option<Skip<'a>> {
+    match skip {
+        Some(Skip::LinesOnce(_)) | Some(Skip::BytesOnce(_)) if exempted => None,
+        Some(Skip::LinesOnce(n)) => Some(Skip::Lines(*n)),
+        Some(Skip::BytesOnce(n)) => Some(Skip::Bytes(*n)),
+        other => other.clone(),
+    }
+}
+
+// Returns the bytes of the given newline style.
+fn newline_bytes(newline: Newline) -> &'static [u8] {
+    match newline {
+        Newline::Lf => b"\n",
+        Newline::Crlf => b"\r\n",
+    }
+}
+
+// Returns a hash of the contents of the given stream, rewinding it afterwards.
+fn digest<RS: Read + Seek>(stream: &mut RS) -> io::Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    let mut buf = [0; 8192];
+    stream.seek(SeekFrom::Start(0))?;
+    loop {
+        match stream.read(&mut buf)? {
+            0 => break,
+            n => hasher.write(&buf[..n]),
+        }
+    }
+    stream.seek(SeekFrom::Start(0))?;
+    Ok(hasher.finish())
+}
+
+// Returns the newline style making up most of the last bytes of the given stream, or `None` if
+// there is no newline among them, rewinding it afterwards.
+fn detect_newline<RS: Read + Seek>(stream: &mut RS) -> io::Result<Option<Newline>> {
+    const SAMPLE: u64 = 512;
+
+    let len = stream.seek(SeekFrom::End(0))?;
+    stream.seek(SeekFrom::Start(len.saturating_sub(SAMPLE)))?;
+    let mut sample = Vec::new();
+    stream.by_ref().take(SAMPLE).read_to_end(&mut sample)?;
+    stream.seek(SeekFrom::Start(0))?;
+
+    let lf = sample.iter().filter(|&&b| b == b'\n').count();
+    let crlf = sample.windows(2).filter(|w| w == b"\r\n").count();
+    Ok(match (lf, crlf) {
+        (0, _) => None,
+        (lf, crlf) if crlf * 2 > lf => Some(Newline::Crlf),
+        _ => Some(Newline::Lf),
+    })
+}
@@ -0,0 +1,671 @@
+//! Writer adapters wrapped around the final output.
+use std::error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// The temporary file of the `AtomicFile` being written, if any.
+static PENDING: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Installs a Ctrl-C handler that makes every [`Interruptible`] writer stop accepting writes.
+pub fn catch_interrupt() -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::SeqCst))
+}
+
+/// Installs a Ctrl-C handler that removes the temporary file of any uncommitted [`AtomicFile`]
+/// before exiting, since the process would otherwise be killed before the file gets dropped.
+pub fn clean_up_on_interrupt() -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(|| {
+        if let Some(tmp) = PENDING.lock().ok().and_then(|mut pending| pending.take()) {
+            let _ = fs::remove_file(tmp);
+        }
+        std::process::exit(130);
+    })
+}
+
+/// Returns `true` if a Ctrl-C has been caught.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// An output file that is written under a temporary sibling name, and only moved into place by
+/// [`commit`], so the destination never ends up half-written. The temporary file is removed if
+/// the `AtomicFile` is dropped without being committed.
+///
+/// Symlinks are resolved first, so the file they point to gets replaced rather than the link
+/// itself. Destinations that are not writable regular files (FIFOs, devices such as
+/// `/dev/stdout`, read-only files) are written in place instead, as renaming over them would
+/// either fail or replace them with a regular file. So are files in a directory the temporary file
+/// cannot be created in.
+///
+/// [`commit`]: AtomicFile::commit
+pub struct AtomicFile {
+    file: File,
+    tmp: Option<PathBuf>,
+    path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicFile {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = match fs::canonicalize(&path) {
+            Ok(resolved) => resolved,
+            Err(_) => path.as_ref().to_path_buf(),
+        };
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => Some(metadata),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let in_place = |path: PathBuf| {
+            Ok(AtomicFile {
+                file: File::create(&path)?,
+                tmp: None,
+                path,
+                committed: false,
+            })
+        };
+        if let Some(metadata) = &metadata {
+            if !metadata.is_file() || metadata.permissions().readonly() {
+                return in_place(path);
+            }
+        }
+
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid output path"))?;
+        let mut tmp_name = std::ffi::OsString::from(".");
+        tmp_name.push(name);
+        tmp_name.push(format!(".{}.tmp", std::process::id()));
+        let tmp = path.with_file_name(tmp_name);
+
+        let file = match OpenOptions::new().write(true).create_new(true).open(&tmp) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => return in_place(path),
+            Err(e) => return Err(e),
+        };
+        *PENDING.lock().unwrap_or_else(|e| e.into_inner()) = Some(tmp.clone());
+        let atomic = AtomicFile {
+            file,
+            tmp: Some(tmp),
+            path,
+            committed: false,
+        };
+        // Keeps the permissions of the file being replaced.
+        if let Some(metadata) = metadata {
+            atomic.file.set_permissions(metadata.permissions())?;
+        }
+
+        Ok(atomic)
+    }
+
+    /// Flushes the written contents and renames the temporary file over the destination.
+    pub fn commit(mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if let Some(tmp) = &self.tmp {
+            // Holds the lock while renaming, so that Ctrl-C cannot remove the file midway.
+            let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+            fs::rename(tmp, &self.path)?;
+            *pending = None;
+        }
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if let (Some(tmp), false) = (&self.tmp, self.committed) {
+            let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+            let _ = fs::remove_file(tmp);
+            *pending = None;
+        }
+    }
+}
+
+/// The error an [`Interruptible`] writer fails with once its limit has been reached.
+#[derive(Debug)]
+pub struct LimitReached;
+
+impl fmt::Display for LimitReached {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "output limit reached")
+    }
+}
+
+impl error::Error for LimitReached {}
+
+/// Returns `true` if the given error is an [`Interruptible`] writer refusing to write past its
+/// limit.
+pub fn is_limit_reached(e: &io::Error) -> bool {
+    e.get_ref().is_some_and(|e| e.is::<LimitReached>())
+}
+
+/// A writer that counts the bytes written into it, and fails any further write once a Ctrl-C
+/// has been caught or its limit has been reached, so the merge unwinds and the output can be
+/// flushed and closed cleanly.
+pub struct Interruptible<W> {
+    inner: W,
+    written: u64,
+    limit: Option<u64>,
+}
+
+impl<W: Write> Interruptible<W> {
+    pub fn new(inner: W) -> Self {
+        Interruptible {
+            inner,
+            written: 0,
+            limit: None,
+        }
+    }
+
+    /// Stops accepting writes once the given number of bytes has been written, cutting the last
+    /// write short if needed.
+    pub fn limit(&mut self, limit: u64) -> &mut Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+}
+
+impl<W: Write> Write for Interruptible<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if interrupted() {
+            // `ErrorKind::Interrupted` would be retried by `write_all`.
+            return Err(io::Error::other("interrupted"));
+        }
+        let buf = match self.limit {
+            Some(limit) if self.written >= limit => {
+                return Err(io::Error::other(LimitReached));
+            }
+            Some(limit) => &buf[..buf.len().min((limit - self.written) as usize)],
+            None => buf,
+        };
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A writer that copies everything written into it to two inner writers.
+pub struct Tee<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: Write, B: Write> Tee<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Tee { first, second }
+    }
+}
+
+impl<A: Write, B: Write> Write for Tee<A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.first.write_all(buf)?;
+        self.second.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.first.flush()?;
+        self.second.flush()
+    }
+}
+
+/// A writer that rewrites every unquoted `from` delimiter into `to`.
+///
+/// This is a lightweight transform rather than a CSV parser: a field is considered quoted between
+/// a pair of `"`, and the quoting state is reset at the end of every line.
+pub struct DelimiterNormalizer<W> {
+    inner: W,
+    from: u8,
+    to: u8,
+    quoted: bool,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> DelimiterNormalizer<W> {
+    pub fn new(inner: W, from: u8, to: u8) -> Self {
+        DelimiterNormalizer {
+            inner,
+            from,
+            to,
+            quoted: false,
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> Write for DelimiterNormalizer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.clear();
+        for &b in buf {
+            match b {
+                b'"' => self.quoted = !self.quoted,
+                b'\n' => self.quoted = false,
+                _ => (),
+            }
+            if b == self.from && !self.quoted {
+                self.buf.push(self.to);
+            } else {
+                self.buf.push(b);
+            }
+        }
+        self.inner.write_all(&self.buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A writer that drops the final `\n` or `\r\n` of everything written into it.
+///
+/// A trailing line ending is held back until more data follows, so it never reaches the inner
+/// writer if nothing does. A held back lone `\r` is written out on [`flush`].
+///
+/// [`flush`]: Write::flush
+pub struct TrailingNewlineTrimmer<W> {
+    inner: W,
+    held: Vec<u8>,
+}
+
+impl<W: Write> TrailingNewlineTrimmer<W> {
+    pub fn new(inner: W) -> Self {
+        TrailingNewlineTrimmer {
+            inner,
+            held: Vec::with_capacity(2),
+        }
+    }
+}
+
+impl<W: Write> Write for TrailingNewlineTrimmer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let (body, tail) = match buf {
+            [body @ .., b'\r', b'\n'] | [body @ .., b'\n'] | [body @ .., b'\r'] => {
+                (body, &buf[body.len()..])
+            }
+            body => (body, &[][..]),
+        };
+        // A held back `\r` completes a `\r\n` split across two writes.
+        if body.is_empty() && self.held == b"\r" && tail == b"\n" {
+            self.held.push(b'\n');
+            return Ok(buf.len());
+        }
+
+        self.inner.write_all(&self.held)?;
+        self.inner.write_all(body)?;
+        self.held.clear();
+        self.held.extend_from_slice(tail);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.held == b"\r" {
+            self.inner.write_all(&self.held)?;
+            self.held.clear();
+        }
+        self.inner.flush()
+    }
+}
+
+/// How [`LineNumberer`] writes line numbers, parsed from a format holding a single `{}`
+/// placeholder for the number, optionally with a width as in `{:6}`, `{:>6}`, `{:<6}` or `{:06}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumberFormat {
+    before: String,
+    after: String,
+    align: Align,
+    width: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+    Zero,
+}
+
+impl Default for NumberFormat {
+    /// Right-aligns numbers on 6 columns, followed by a tab, like `cat -n` does.
+    fn default() -> Self {
+        NumberFormat {
+            before: String::new(),
+            after: String::from("\t"),
+            align: Align::Right,
+            width: 6,
+        }
+    }
+}
+
+impl std::str::FromStr for NumberFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = match (s.find('{'), s.find('}')) {
+            (Some(start), Some(end)) if start < end => (start, end),
+            _ => return Err(String::from("expected a `{}` placeholder for the number")),
+        };
+        let (before, spec, after) = (&s[..start], &s[start + 1..end], &s[end + 1..]);
+        if after.contains(&['{', '}'][..]) {
+            return Err(String::from("expected a single `{}` placeholder"));
+        }
+
+        let spec = match spec.strip_prefix(':') {
+            Some(spec) => spec,
+            None if spec.is_empty() => "",
+            None => return Err(format!("invalid placeholder `{{{}}}`", spec)),
+        };
+        let (align, width) = match spec.as_bytes().first() {
+            Some(b'<') => (Align::Left, &spec[1..]),
+            Some(b'>') => (Align::Right, &spec[1..]),
+            Some(b'0') => (Align::Zero, &spec[1..]),
+            _ => (Align::Right, spec),
+        };
+        let width = match width {
+            "" => 0,
+            width => width
+                .parse::<usize>()
+                .map_err(|_| format!("invalid placeholder `{{:{}}}`", spec))?,
+        };
+        // Wider widths make `write!` panic.
+        if width > u16::MAX as usize {
+            return Err(format!("width {} is larger than {}", width, u16::MAX));
+        }
+
+        Ok(NumberFormat {
+            before: before.to_string(),
+            after: after.to_string(),
+            align,
+            width,
+        })
+    }
+}
+
+/// A writer that prefixes every line written into it with its number in the given format,
+/// counting from 1 across all writes.
+pub struct LineNumberer<W> {
+    inner: W,
+    format: NumberFormat,
+    line: u64,
+    at_line_start: bool,
+}
+
+impl<W: Write> LineNumberer<W> {
+    pub fn new(inner: W, format: NumberFormat) -> Self {
+        LineNumberer {
+            inner,
+            format,
+            line: 0,
+            at_line_start: true,
+        }
+    }
+}
+
+impl<W: Write> Write for LineNumberer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for line in buf.split_inclusive(|&b| b == b'\n') {
+            if self.at_line_start {
+                self.line += 1;
+                let NumberFormat {
+                    before,
+                    after,
+                    align,
+                    width,
+                } = &self.format;
+                let (n, width) = (self.line, *width);
+                match align {
+                    Align::Left => write!(self.inner, "{}{:<width$}{}", before, n, after)?,
+                    Align::Right => write!(self.inner, "{}{:>width$}{}", before, n, after)?,
+                    Align::Zero => write!(self.inner, "{}{:0width$}{}", before, n, after)?,
+                }
+            }
+            self.inner.write_all(line)?;
+            self.at_line_start = line.ends_with(b"\n");
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A writer that makes line ends and tabs visible like `cat -E` and `cat -T` do. Line ends get a
+/// `$` before every `\n` and render every `\r` as `^M`, while tabs render as `^I`.
+pub struct WhitespaceMarker<W> {
+    inner: W,
+    ends: bool,
+    tabs: bool,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> WhitespaceMarker<W> {
+    pub fn new(inner: W, ends: bool, tabs: bool) -> Self {
+        WhitespaceMarker {
+            inner,
+            ends,
+            tabs,
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> Write for WhitespaceMarker<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.clear();
+        for &b in buf {
+            match b {
+                b'\n' if self.ends => self.buf.extend_from_slice(b"$\n"),
+                b'\r' if self.ends => self.buf.extend_from_slice(b"^M"),
+                b'\t' if self.tabs => self.buf.extend_from_slice(b"^I"),
+                b => self.buf.push(b),
+            }
+        }
+        self.inner.write_all(&self.buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A writer that collapses every run of empty lines written into it into a single one, like
+/// `cat -s` does. Lines made of whitespace are not empty.
+///
+/// A lone `\r` starting a line is held back until it is known whether it ends an empty line, and
+/// is written out on [`flush`] if nothing follows.
+///
+/// [`flush`]: Write::flush
+pub struct BlankSqueezer<W> {
+    inner: W,
+    at_line_start: bool,
+    blank: bool,
+    held: Vec<u8>,
+}
+
+impl<W: Write> BlankSqueezer<W> {
+    pub fn new(inner: W) -> Self {
+        BlankSqueezer {
+            inner,
+            at_line_start: true,
+            blank: false,
+            held: Vec::with_capacity(2),
+        }
+    }
+}
+
+impl<W: Write> Write for BlankSqueezer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for part in buf.split_inclusive(|&b| b == b'\n') {
+            self.held.extend_from_slice(part);
+            if self.at_line_start {
+                match self.held.as_slice() {
+                    b"\r" => continue,
+                    b"\n" | b"\r\n" => {
+                        if !self.blank {
+                            self.inner.write_all(&self.held)?;
+                        }
+                        self.blank = true;
+                        self.held.clear();
+                        continue;
+                    }
+                    _ => self.blank = false,
+                }
+            }
+            self.inner.write_all(&self.held)?;
+            self.at_line_start = self.held.ends_with(b"\n");
+            self.held.clear();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.held.is_empty() {
+            self.inner.write_all(&self.held)?;
+            self.at_line_start = false;
+            self.blank = false;
+            self.held.clear();
+        }
+        self.inner.flush()
+    }
+}
+
+/// A writer that only passes on the lines accepted by a predicate, which is handed each line
+/// along with its line ending.
+///
+/// A line is held back until its `\n` has been written. Call [`finish`] to hand over an
+/// unterminated last line.
+///
+/// [`finish`]: LineFilter::finish
+pub struct LineFilter<W, F> {
+    inner: W,
+    accept: F,
+    line: Vec<u8>,
+}
+
+impl<W: Write, F: FnMut(&[u8]) -> bool> LineFilter<W, F> {
+    pub fn new(inner: W, accept: F) -> Self {
+        LineFilter {
+            inner,
+            accept,
+            line: Vec::new(),
+        }
+    }
+
+    /// Writes out the unterminated last line if it gets accepted.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if !self.line.is_empty() && (self.accept)(&self.line) {
+            self.inner.write_all(&self.line)?;
+        }
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write, F: FnMut(&[u8]) -> bool> Write for LineFilter<W, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for line in buf.split_inclusive(|&b| b == b'\n') {
+            self.line.extend_from_slice(line);
+            if line.ends_with(b"\n") {
+                if (self.accept)(&self.line) {
+                    self.inner.write_all(&self.line)?;
+                }
+                self.line.clear();
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A writer that counts the bytes and lines passing through it.
+pub struct Counter<W> {
+    inner: W,
+    bytes: u64,
+    newlines: u64,
+    last: Option<u8>,
+}
+
+impl<W: Write> Counter<W> {
+    pub fn new(inner: W) -> Self {
+        Counter {
+            inner,
+            bytes: 0,
+            newlines: 0,
+            last: None,
+        }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Returns the number of newlines written so far.
+    pub fn newlines(&self) -> u64 {
+        self.newlines
+    }
+
+    /// Returns the last byte written so far, if any.
+    pub fn last(&self) -> Option<u8> {
+        self.last
+    }
+
+    /// Returns the number of lines written so far, counting an unterminated last line.
+    pub fn lines(&self) -> u64 {
+        match self.last {
+            Some(b'\n') | None => self.newlines,
+            Some(_) => self.newlines + 1,
+        }
+    }
+}
+
+impl<W: Write> Write for Counter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        let written = &buf[..n];
+        self.bytes += n as u64;
+        self.newlines += written.iter().filter(|&&b| b == b'\n').count() as u64;
+        if let Some(&b) = written.last() {
+            self.last = Some(b);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
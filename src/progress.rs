@@ -0,0 +1,22 @@
+//! Renders the progress of a merge on stderr.
+use crate::error::{Error, Result};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+use std::path::PathBuf;
+
+/// Creates a progress bar over the total size of the given paths, to be advanced by the number of
+/// bytes written.
+pub fn bar(paths: &[PathBuf]) -> Result<ProgressBar> {
+    let total = paths.iter().try_fold(0, |total, path| {
+        let len = path.metadata().map_err(|e| Error::file(path, e))?.len();
+        Ok::<_, Error>(total + len)
+    })?;
+
+    let bar = ProgressBar::new(total);
+    bar.set_style(
+        ProgressStyle::with_template("{bar:40} {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+            .expect("valid progress template"),
+    );
+    Ok(bar)
+}
@@ -0,0 +1,193 @@
+//! Input sources handed to the merger.
+use crate::error::{Error, Result};
+
+use std::fs::File;
+use std::io::{self, prelude::*, BufReader, SeekFrom};
+use std::path::PathBuf;
+
+/// The UTF-8 byte order mark.
+pub const BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+
+/// Checks that every given path points to a regular file, before anything gets written.
+///
+/// Returns [`Error::InvalidPath`] carrying the first offending path and its index.
+pub fn validate(paths: &[PathBuf]) -> Result<()> {
+    match paths.iter().position(|path| !path.is_file()) {
+        Some(index) => Err(Error::InvalidPath {
+            index,
+            path: paths[index].clone(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Drops every path that does not point to a readable regular file, handing the reason to
+/// `on_error` instead of failing.
+pub fn retain_valid<F: FnMut(Error)>(paths: &mut Vec<PathBuf>, mut on_error: F) {
+    let mut index = 0;
+    paths.retain(|path| {
+        let valid = match path.is_file() {
+            false => Err(Error::InvalidPath {
+                index,
+                path: path.clone(),
+            }),
+            true => File::open(path).map_err(|e| Error::file(path, e)),
+        };
+        index += 1;
+        valid.map_err(&mut on_error).is_ok()
+    });
+}
+
+/// Drops every path pointing to an empty file, so that it contributes neither contents nor
+/// paddings.
+pub fn retain_non_empty(paths: &mut Vec<PathBuf>) -> Result<()> {
+    let mut lens = Vec::with_capacity(paths.len());
+    for path in paths.iter() {
+        lens.push(path.metadata().map_err(|e| Error::file(path, e))?.len());
+    }
+    let mut lens = lens.into_iter();
+    paths.retain(|_| lens.next() != Some(0));
+    Ok(())
+}
+
+/// A readable and seekable window over the remaining contents of an inner stream.
+///
+/// The merger only ever sees the contents inside the window, so narrowing the window from the
+/// head (e.g. to drop comment lines) happens before any of the configured skips are applied.
+pub struct Source<R> {
+    inner: R,
+    start: u64,
+    end: u64,
+    pos: u64,
+}
+
+impl<R: Read + Seek> Source<R> {
+    /// Creates a window covering the entire contents of the given stream.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let end = inner.seek(SeekFrom::End(0))?;
+        inner.seek(SeekFrom::Start(0))?;
+        Ok(Source {
+            inner,
+            start: 0,
+            end,
+            pos: 0,
+        })
+    }
+
+    /// Returns the length of the window.
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    /// Drops a leading UTF-8 byte order mark from the window.
+    ///
+    /// Returns `true` if a byte order mark has been dropped.
+    pub fn skip_bom(&mut self) -> io::Result<bool> {
+        self.rewind()?;
+        let mut head = [0; 3];
+        let n = self.by_ref().take(3).read(&mut head)?;
+        let skipped = n == 3 && head == BOM;
+        if skipped {
+            self.start += 3;
+        }
+        self.rewind()?;
+        Ok(skipped)
+    }
+
+    /// Drops every leading line that begins with the given prefix from the window.
+    pub fn skip_lines_starting_with(&mut self, prefix: &[u8]) -> io::Result<()> {
+        self.inner.seek(SeekFrom::Start(self.start))?;
+        let mut reader = BufReader::new(&mut self.inner);
+        let mut line = Vec::new();
+        let mut skipped = 0;
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 || !line.starts_with(prefix) {
+                break;
+            }
+            skipped += line.len() as u64;
+        }
+
+        self.start += skipped;
+        self.rewind()
+    }
+
+    /// Returns the first line of the window without its line ending, or `None` if the window is
+    /// empty.
+    pub fn head_line(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let line = self.read_head_line()?;
+        self.rewind()?;
+        Ok(line.map(|(line, _)| line))
+    }
+
+    /// Drops the first line from the window if it equals the given line, ignoring line endings.
+    ///
+    /// Returns `true` if the line has been dropped.
+    pub fn skip_head_line_if(&mut self, expected: &[u8]) -> io::Result<bool> {
+        let skipped = match self.read_head_line()? {
+            Some((line, len)) if line == expected => {
+                self.start += len;
+                true
+            }
+            _ => false,
+        };
+        self.rewind()?;
+        Ok(skipped)
+    }
+
+    // Reads the first line of the window, returning it without its line ending along with the
+    // number of bytes it spans.
+    fn read_head_line(&mut self) -> io::Result<Option<(Vec<u8>, u64)>> {
+        self.rewind()?;
+        let mut line = Vec::new();
+        let len = BufReader::new(self.by_ref()).read_until(b'\n', &mut line)? as u64;
+        if len == 0 {
+            return Ok(None);
+        }
+        if line.ends_with(b"\n") {
+            line.pop();
+            if line.ends_with(b"\r") {
+                line.pop();
+            }
+        }
+        Ok(Some((line, len)))
+    }
+
+    // Moves the cursor back to the start of the window.
+    fn rewind(&mut self) -> io::Result<()> {
+        self.pos = 0;
+        self.inner.seek(SeekFrom::Start(self.start))?;
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> Read for Source<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len().saturating_sub(self.pos);
+        let max = (buf.len() as u64).min(remaining) as usize;
+        let n = self.inner.read(&mut buf[..max])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek> Seek for Source<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.len().checked_add_signed(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+        };
+        match pos {
+            Some(pos) => {
+                self.pos = pos;
+                self.inner.seek(SeekFrom::Start(self.start + pos))?;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! Transcodes sources into UTF-8 ahead of merging.
+use crate::error::{Error, Result};
+
+use encoding_rs::{Encoding, UTF_8};
+use tempfile::NamedTempFile;
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Replaces every given path whose contents start with a UTF-16 byte order mark with a temporary
+/// file holding its contents transcoded into UTF-8, byte order mark dropped, so that skips apply
+/// to the decoded lines. Other paths, including those without any byte order mark, are kept as is.
+///
+/// The temporary files are removed once the returned handles are dropped.
+pub fn spool_utf8(paths: &mut [PathBuf]) -> Result<Vec<NamedTempFile>> {
+    let mut spools = Vec::new();
+    for path in paths.iter_mut() {
+        let bytes = fs::read(&path).map_err(|e| Error::file(&*path, e))?;
+        let (encoding, bom) = match Encoding::for_bom(&bytes) {
+            Some((encoding, _)) if encoding == UTF_8 => continue,
+            Some(detected) => detected,
+            None => continue,
+        };
+
+        let (decoded, _) = encoding.decode_without_bom_handling(&bytes[bom..]);
+        let mut spool = NamedTempFile::new()?;
+        spool.write_all(decoded.as_bytes())?;
+        spool.flush()?;
+        *path = spool.path().to_path_buf();
+        spools.push(spool);
+    }
+
+    Ok(spools)
+}
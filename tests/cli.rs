@@ -60,6 +60,7 @@ fn can_accept_space_separated_paths_through_stdin() {
         files[1].path().to_str().unwrap(),
         files[2].path().to_str().unwrap()
     ))
+    .arg("--paths-space")
     .assert()
     .stdout(predicate::eq(
         b"111 112\n121 122\n131 132\n211 212\n221 222\n231 232\n311 312\n332 322\n331 332"
@@ -88,13 +89,6 @@ fn can_accept_newline_separated_paths_through_stdin() {
 fn can_write_output_to_a_file() {
     let dir = tempdir().unwrap();
     let file_path = dir.path().join("output.txt");
-    let mut file = OpenOptions::new()
-        .create(true)
-        .read(true)
-        .write(true)
-        .append(true)
-        .open(file_path.clone())
-        .unwrap();
     let input_files = testing_files!();
     let mut cmd = Command::cargo_bin("fcc").unwrap();
     cmd.write_stdin(format!(
@@ -107,6 +101,8 @@ fn can_write_output_to_a_file() {
     .arg(file_path.to_str().unwrap())
     .unwrap();
 
+    // The output is renamed into place, so it has to be opened after the merge.
+    let mut file = OpenOptions::new().read(true).open(&file_path).unwrap();
     let mut buf = Vec::new();
     file.read_to_end(&mut buf).unwrap();
     assert_eq!(
@@ -625,6 +621,25 @@ fn arg_padding_works_as_expected() {
     ));
 }
 
+#[test]
+fn arg_pad_mode_works_with_a_single_source() {
+    let files = testing_files!();
+    for (mode, expected) in [
+        ("between", "111 112\n121 122\n131 132\n"),
+        ("afterend", "111 112\n121 122\n131 132\n=="),
+        ("beforestart", "==111 112\n121 122\n131 132\n"),
+        ("all", "==111 112\n121 122\n131 132\n=="),
+    ] {
+        let mut cmd = Command::cargo_bin("fcc").unwrap();
+        cmd.arg("-i")
+            .arg(files[0].path())
+            .args(["-p", "==", "-P", mode])
+            .assert()
+            .success()
+            .stdout(predicate::eq(expected));
+    }
+}
+
 #[test]
 fn arg_newline_works_as_expected() {
     let files = testing_files!();
@@ -795,3 +810,1800 @@ fn skip_tails_cannot_provide_at_the_same_time() {
     .assert()
     .failure();
 }
+
+#[test]
+fn arg_interleave_works_as_expected() {
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path().to_str().unwrap())
+        .arg(files[1].path().to_str().unwrap())
+        .arg(files[2].path().to_str().unwrap())
+        .arg("--interleave")
+        .arg("2")
+        .assert()
+        .stdout(predicate::eq(
+            b"111 112\n121 122\n211 212\n221 222\n311 312\n332 322\n131 132\n231 232\n331 332\n"
+                as &[u8],
+        ));
+
+    // Exhausted sources are dropped from the rotation.
+    let mut short = NamedTempFile::new().unwrap();
+    writeln!(short, "011 012").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(short.path().to_str().unwrap())
+        .arg(files[0].path().to_str().unwrap())
+        .arg("--interleave")
+        .arg("2")
+        .assert()
+        .stdout(predicate::eq(
+            b"011 012\n111 112\n121 122\n131 132\n" as &[u8],
+        ));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path().to_str().unwrap())
+        .arg("--interleave")
+        .arg("0")
+        .assert()
+        .failure();
+}
+
+// Creates a source large enough not to fit in a pipe, made of numbered 9-byte lines.
+#[cfg(unix)]
+fn large_file() -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    let lines = (0..100_000)
+        .map(|i| format!("{:08}\n", i))
+        .collect::<String>();
+    file.write_all(lines.as_bytes()).unwrap();
+    file
+}
+
+// Runs the given command, sending it a Ctrl-C once it has written part of its output to stdout,
+// then returns its exit code, its whole stdout and its stderr.
+#[cfg(unix)]
+fn interrupt_midway(cmd: &mut std::process::Command) -> (Option<i32>, Vec<u8>, String) {
+    use std::process::{Command as StdCommand, Stdio};
+
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let mut written = vec![0; 4096];
+    // The merge is under way once something shows up, and blocks once the pipe is full.
+    stdout.read_exact(&mut written).unwrap();
+    StdCommand::new("kill")
+        .arg("-INT")
+        .arg(child.id().to_string())
+        .status()
+        .unwrap();
+    stdout.read_to_end(&mut written).unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    (output.status.code(), written, stderr)
+}
+
+#[cfg(unix)]
+#[test]
+fn arg_flush_on_interrupt_reports_progress() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("output.txt");
+    let large = large_file();
+    let (code, written, stderr) = interrupt_midway(
+        std::process::Command::new(assert_cmd::cargo::cargo_bin("fcc"))
+            .arg("-i")
+            .arg(large.path())
+            .arg("-o")
+            .arg(&file_path)
+            .args(["--tee", "--flush-on-interrupt"]),
+    );
+    assert_eq!(code, Some(130));
+    assert_eq!(
+        stderr,
+        format!("fcc: interrupted after writing {} bytes\n", written.len())
+    );
+    // What was written before Ctrl-C is committed, and nothing after.
+    let contents = std::fs::read(large.path()).unwrap();
+    assert!(written.len() < contents.len());
+    assert_eq!(written, contents[..written.len()]);
+    assert_eq!(std::fs::read(&file_path).unwrap(), written);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn arg_columns_works_as_expected() {
+    let files = testing_files!();
+    let mut short = NamedTempFile::new().unwrap();
+    writeln!(short, "011 012").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path().to_str().unwrap())
+        .arg(short.path().to_str().unwrap())
+        .arg("--columns")
+        .arg(",")
+        .assert()
+        .stdout(predicate::eq(
+            b"111 112,011 012\n121 122,\n131 132,\n" as &[u8],
+        ));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(short.path().to_str().unwrap())
+        .arg(files[2].path().to_str().unwrap())
+        .arg("--columns")
+        .arg("\t")
+        .arg("-N")
+        .arg("crlf")
+        .assert()
+        .stdout(predicate::eq(
+            b"011 012\t311 312\r\n\t332 322\r\n\t331 332\r\n" as &[u8],
+        ));
+}
+
+#[test]
+fn arg_normalize_delimiter_works_as_expected() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    let mut file2 = NamedTempFile::new().unwrap();
+    write!(file1, "a,b,c\n1,\"x;y\",3\n").unwrap();
+    write!(file2, "4;\"5;6\";7\n8;9;\"10,11\"\n").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path().to_str().unwrap())
+        .arg(file2.path().to_str().unwrap())
+        .arg("--normalize-delimiter")
+        .arg(";")
+        .arg(",")
+        .assert()
+        .stdout(predicate::eq(
+            b"a,b,c\n1,\"x;y\",3\n4,\"5;6\",7\n8,9,\"10,11\"\n" as &[u8],
+        ));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path().to_str().unwrap())
+        .arg("--normalize-delimiter")
+        .arg(";;")
+        .arg(",")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn arg_skip_prefix_works_as_expected() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    let mut file2 = NamedTempFile::new().unwrap();
+    let mut file3 = NamedTempFile::new().unwrap();
+    write!(file1, "h1,h2\n11,12\n").unwrap();
+    write!(file2, "# comment\nh1,h2\n21,22\n").unwrap();
+    write!(file3, "# comment\n#\n# comment\nh1,h2\n31,32\n# trailing\n").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path().to_str().unwrap())
+        .arg(file2.path().to_str().unwrap())
+        .arg(file3.path().to_str().unwrap())
+        .arg("--skip-prefix")
+        .arg("#")
+        .assert()
+        .stdout(predicate::eq(
+            b"h1,h2\n11,12\nh1,h2\n21,22\nh1,h2\n31,32\n# trailing\n" as &[u8],
+        ));
+
+    // The header is taken after comment lines are dropped.
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file3.path().to_str().unwrap())
+        .arg(file2.path().to_str().unwrap())
+        .arg(file1.path().to_str().unwrap())
+        .arg("--skip-prefix")
+        .arg("#")
+        .arg("-H")
+        .assert()
+        .stdout(predicate::eq(
+            b"h1,h2\n31,32\n# trailing\n21,22\n11,12\n" as &[u8],
+        ));
+}
+
+#[test]
+fn arg_dry_run_works_as_expected() {
+    let files = testing_files!();
+    let args = ["-n", "-S", "1", "-p", "==\n"];
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    let merged = cmd
+        .arg("-i")
+        .arg(files[0].path())
+        .arg(files[1].path())
+        .arg(files[2].path())
+        .args(args)
+        .output()
+        .unwrap()
+        .stdout;
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(files[1].path())
+        .arg(files[2].path())
+        .args(args)
+        .arg("--dry-run")
+        .assert()
+        .stdout(predicate::str::similar(format!(
+            "{}: 24 bytes, 3 lines\n{}: 16 bytes, 2 lines\n{}: 16 bytes, 2 lines, newline appended\ntotal: {} bytes\n",
+            files[0].path().display(),
+            files[1].path().display(),
+            files[2].path().display(),
+            merged.len(),
+        )));
+}
+
+#[test]
+fn arg_dry_run_totals_the_transformed_output() {
+    let files = testing_files!();
+    let mut file = NamedTempFile::new().unwrap();
+    write!(file, "a\tb;c\n\n\n\nd\r\n").unwrap();
+    for args in [
+        &["--number"][..],
+        &["--trim-trailing-newline"],
+        &["--limit-bytes", "30"],
+        &["--squeeze-blank"],
+        &["--show-ends", "--show-tabs"],
+        &["--normalize-delimiter", ";", ","],
+        &["--bom", "--start-with", "[", "--limit-bytes", "12"],
+    ] {
+        let mut cmd = Command::cargo_bin("fcc").unwrap();
+        let merged = cmd
+            .arg("-i")
+            .arg(files[0].path())
+            .arg(file.path())
+            .args(args)
+            .output()
+            .unwrap()
+            .stdout;
+
+        let mut cmd = Command::cargo_bin("fcc").unwrap();
+        cmd.arg("-i")
+            .arg(files[0].path())
+            .arg(file.path())
+            .args(args)
+            .arg("--dry-run")
+            .assert()
+            .success()
+            .stdout(predicate::str::ends_with(format!(
+                "total: {} bytes\n",
+                merged.len()
+            )));
+    }
+}
+
+#[test]
+fn invalid_paths_are_reported_before_writing() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("output.txt");
+    std::fs::write(&file_path, "untouched").unwrap();
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(dir.path().join("missing.txt"))
+        .arg(files[2].path())
+        .arg("-o")
+        .arg(&file_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("index 1"));
+    assert_eq!(std::fs::read(&file_path).unwrap(), b"untouched");
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(dir.path())
+        .assert()
+        .failure()
+        .stdout(predicate::eq(b"" as &[u8]));
+}
+
+#[test]
+fn arg_lenient_skip_works_as_expected() {
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg("-s")
+        .arg("5")
+        .assert()
+        .failure();
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg("-s")
+        .arg("5")
+        .arg("--lenient-skip")
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"" as &[u8]));
+
+    let mut short = NamedTempFile::new().unwrap();
+    writeln!(short, "011 012").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(short.path())
+        .arg(files[1].path())
+        .arg("-s")
+        .arg("2")
+        .arg("-p")
+        .arg("|")
+        .arg("--lenient-skip")
+        .assert()
+        .stdout(predicate::eq(b"131 132\n||231 232\n" as &[u8]));
+}
+
+#[test]
+fn output_file_is_replaced_atomically() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("output.txt");
+    std::fs::write(
+        &file_path,
+        "a much longer previous output that must not leak through",
+    )
+    .unwrap();
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg("-o")
+        .arg(&file_path)
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read(&file_path).unwrap(),
+        b"111 112\n121 122\n131 132\n"
+    );
+
+    // A merge failing midway leaves the previous output in place and no temporary file behind.
+    let mut long = NamedTempFile::new().unwrap();
+    write!(long, "1\n2\n3\n4\n5\n").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(long.path())
+        .arg(files[0].path())
+        .arg("-s")
+        .arg("4")
+        .arg("-o")
+        .arg(&file_path)
+        .assert()
+        .failure();
+    assert_eq!(
+        std::fs::read(&file_path).unwrap(),
+        b"111 112\n121 122\n131 132\n"
+    );
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn output_symlink_is_written_through() {
+    let dir = tempdir().unwrap();
+    let real = dir.path().join("real.txt");
+    let link = dir.path().join("link.txt");
+    std::fs::write(&real, "previous output").unwrap();
+    std::os::unix::fs::symlink(&real, &link).unwrap();
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg("-o")
+        .arg(&link)
+        .assert()
+        .success();
+    assert!(std::fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(
+        std::fs::read(&real).unwrap(),
+        b"111 112\n121 122\n131 132\n"
+    );
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+}
+
+#[cfg(unix)]
+#[test]
+fn output_fifo_is_written_in_place() {
+    use std::os::unix::fs::FileTypeExt;
+    use std::process::Command as StdCommand;
+
+    let dir = tempdir().unwrap();
+    let fifo = dir.path().join("fifo");
+    assert!(StdCommand::new("mkfifo")
+        .arg(&fifo)
+        .status()
+        .unwrap()
+        .success());
+    let reader = {
+        let fifo = fifo.clone();
+        std::thread::spawn(move || std::fs::read(fifo).unwrap())
+    };
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg("-o")
+        .arg(&fifo)
+        .assert()
+        .success();
+    assert_eq!(reader.join().unwrap(), b"111 112\n121 122\n131 132\n");
+    assert!(std::fs::symlink_metadata(&fifo)
+        .unwrap()
+        .file_type()
+        .is_fifo());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn output_in_a_read_only_directory_is_written_in_place() {
+    use std::fs::Permissions;
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("output.txt");
+    std::fs::write(&file_path, "previous output").unwrap();
+    std::fs::set_permissions(dir.path(), Permissions::from_mode(0o555)).unwrap();
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    let assert = cmd
+        .arg("-i")
+        .arg(files[0].path())
+        .arg("-o")
+        .arg(&file_path)
+        .assert();
+    std::fs::set_permissions(dir.path(), Permissions::from_mode(0o755)).unwrap();
+    assert.success();
+    assert_eq!(
+        std::fs::read(&file_path).unwrap(),
+        b"111 112\n121 122\n131 132\n"
+    );
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn output_temporary_file_is_removed_on_interrupt() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("output.txt");
+    std::fs::write(&file_path, "previous output").unwrap();
+    let large = large_file();
+    let (code, written, _) = interrupt_midway(
+        std::process::Command::new(assert_cmd::cargo::cargo_bin("fcc"))
+            .arg("-i")
+            .arg(large.path())
+            .arg("-o")
+            .arg(&file_path)
+            .arg("--tee"),
+    );
+    assert_eq!(code, Some(130));
+    assert!(written.len() < std::fs::metadata(large.path()).unwrap().len() as usize);
+    assert_eq!(std::fs::read(&file_path).unwrap(), b"previous output");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn errors_name_the_offending_path() {
+    let dir = tempdir().unwrap();
+    let missing = dir.path().join("missing.csv");
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(&missing)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(missing.to_str().unwrap()));
+
+    // So do I/O errors on the output file, followed by the underlying message.
+    let output = dir.path().join("missing").join("output.txt");
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg("-o")
+        .arg(&output)
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with(format!(
+            "fcc: {}: ",
+            output.display()
+        )));
+}
+
+#[test]
+fn arg_files_from_works_as_expected() {
+    let files = testing_files!();
+    let mut manifest = NamedTempFile::new().unwrap();
+    write!(
+        manifest,
+        "# inputs\n{}\n\n  {}  \n",
+        files[1].path().to_str().unwrap(),
+        files[2].path().to_str().unwrap()
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("--files-from")
+        .arg(manifest.path())
+        .assert()
+        .stdout(predicate::eq(
+            b"211 212\n221 222\n231 232\n311 312\n332 322\n331 332" as &[u8],
+        ));
+
+    // Composes with `--input`, whose paths come first.
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg("--files-from")
+        .arg(manifest.path())
+        .assert()
+        .stdout(predicate::eq(
+            b"111 112\n121 122\n131 132\n211 212\n221 222\n231 232\n311 312\n332 322\n331 332"
+                as &[u8],
+        ));
+}
+
+#[test]
+fn arg_null_works_as_expected() {
+    let dir = tempdir().unwrap();
+    let spaced = dir.path().join("settings (2).txt");
+    std::fs::write(&spaced, "spaced\n").unwrap();
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.write_stdin(format!(
+        "{}\0{}\0",
+        files[0].path().to_str().unwrap(),
+        spaced.to_str().unwrap()
+    ))
+    .arg("-0")
+    .assert()
+    .stdout(predicate::eq(
+        b"111 112\n121 122\n131 132\nspaced\n" as &[u8],
+    ));
+}
+
+#[test]
+fn can_accept_spaced_paths_through_stdin() {
+    let dir = tempdir().unwrap();
+    let spaced = dir.path().join("settings.json (2).bkp-1");
+    std::fs::write(&spaced, "spaced\n").unwrap();
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.write_stdin(format!(
+        "{}\n{}\n",
+        files[0].path().to_str().unwrap(),
+        spaced.to_str().unwrap()
+    ))
+    .assert()
+    .success()
+    .stdout(predicate::eq(
+        b"111 112\n121 122\n131 132\nspaced\n" as &[u8],
+    ));
+
+    // With `--paths-space`, lines are split on whitespace, unless quoted.
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.write_stdin(format!(
+        "{} \"{}\"\n",
+        files[0].path().to_str().unwrap(),
+        spaced.to_str().unwrap()
+    ))
+    .arg("--paths-space")
+    .assert()
+    .success()
+    .stdout(predicate::eq(
+        b"111 112\n121 122\n131 132\nspaced\n" as &[u8],
+    ));
+}
+
+#[test]
+fn arg_verbose_works_as_expected() {
+    let files = testing_files!();
+    let merged = b"111 112\n121 122\n131 132\n221 222\n231 232\n332 322\n331 332" as &[u8];
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(files[1].path())
+        .arg(files[2].path())
+        .args(["-H", "-v"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(merged))
+        .stderr(predicate::str::similar(format!(
+            "fcc: {}: 24 bytes, 24 bytes written\nfcc: {}: 24 bytes, 16 bytes written\nfcc: {}: 23 bytes, 15 bytes written\n",
+            files[0].path().display(),
+            files[1].path().display(),
+            files[2].path().display(),
+        )));
+
+    // Given twice, also reports what was skipped.
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[1].path())
+        .args(["-s", "1", "-n", "-vv"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"221 222\n231 232\n" as &[u8]))
+        .stderr(predicate::str::similar(format!(
+            "fcc: {0}: 24 bytes, 16 bytes written\nfcc: {0}: 0 bytes dropped up front, 8 bytes skipped\n",
+            files[1].path().display(),
+        )));
+}
+
+#[test]
+fn arg_dedup_headers_works_as_expected() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    let mut file2 = NamedTempFile::new().unwrap();
+    let mut file3 = NamedTempFile::new().unwrap();
+    let mut file4 = NamedTempFile::new().unwrap();
+    write!(file1, "h1,h2\n11,12\n").unwrap();
+    write!(file2, "21,22\n22,23\n").unwrap();
+    write!(file3, "h1,h2\r\n31,32\r\n").unwrap();
+    write!(file4, "h1,h2,h3\n41,42,43\n").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .arg(file3.path())
+        .arg(file4.path())
+        .arg("--dedup-headers")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"h1,h2\n11,12\n21,22\n22,23\n31,32\r\nh1,h2,h3\n41,42,43\n" as &[u8],
+        ));
+
+    // Conflicts with blind head skips.
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .args(["--dedup-headers", "-H"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn arg_input_expands_glob_patterns() {
+    let dir = tempdir().unwrap();
+    for (name, contents) in [
+        ("b.txt", "b\n"),
+        ("c.txt", "c\n"),
+        ("a.txt", "a\n"),
+        ("a.csv", "x\n"),
+    ] {
+        std::fs::write(dir.path().join(name), contents).unwrap();
+    }
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(dir.path().join("*.txt"))
+        .arg(dir.path().join("a.csv"))
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"a\nb\nc\nx\n" as &[u8]));
+}
+
+#[test]
+fn arg_sort_works_as_expected() {
+    let dir = tempdir().unwrap();
+    for name in ["part10", "part2", "part1"] {
+        std::fs::write(dir.path().join(name), format!("{}\n", name)).unwrap();
+    }
+    let stdin = ["part10", "part2", "part1"]
+        .iter()
+        .map(|name| dir.path().join(name).to_str().unwrap().to_owned())
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.write_stdin(stdin.clone())
+        .arg("--sort")
+        .assert()
+        .stdout(predicate::eq(b"part1\npart10\npart2\n" as &[u8]));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.write_stdin(stdin.clone())
+        .arg("--sort=natural")
+        .assert()
+        .stdout(predicate::eq(b"part1\npart2\npart10\n" as &[u8]));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.write_stdin(stdin.clone())
+        .args(["--sort=natural", "--reverse"])
+        .assert()
+        .stdout(predicate::eq(b"part10\npart2\npart1\n" as &[u8]));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.write_stdin(stdin)
+        .arg("--reverse")
+        .assert()
+        .stdout(predicate::eq(b"part1\npart2\npart10\n" as &[u8]));
+}
+
+#[cfg(feature = "progress")]
+#[test]
+fn arg_progress_keeps_output_intact() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("output.txt");
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(files[1].path())
+        .arg(files[2].path())
+        .arg("-o")
+        .arg(&file_path)
+        .arg("--progress=always")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+    assert_eq!(
+        std::fs::read(&file_path).unwrap(),
+        b"111 112\n121 122\n131 132\n211 212\n221 222\n231 232\n311 312\n332 322\n331 332"
+    );
+}
+
+#[test]
+fn arg_trim_trailing_newline_works_as_expected() {
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(files[1].path())
+        .arg("--trim-trailing-newline")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"111 112\n121 122\n131 132\n211 212\n221 222\n231 232" as &[u8],
+        ));
+
+    let mut file = NamedTempFile::new().unwrap();
+    write!(file, "a\r\nb\r\n").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file.path())
+        .arg("--trim-trailing-newline")
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"a\r\nb" as &[u8]));
+
+    // Conflicts with appending newlines.
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .args(["--trim-trailing-newline", "-n"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn arg_recursive_works_as_expected() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("b/d")).unwrap();
+    std::fs::create_dir_all(dir.path().join(".hidden")).unwrap();
+    for (name, contents) in [
+        ("c.txt", "c\n"),
+        ("a.txt", "a\n"),
+        ("b/d/e.txt", "e\n"),
+        ("b/a.txt", "ba\n"),
+        (".dot", "dot\n"),
+        (".hidden/h.txt", "h\n"),
+    ] {
+        std::fs::write(dir.path().join(name), contents).unwrap();
+    }
+    let files = testing_files!();
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(dir.path())
+        .arg(files[0].path())
+        .arg("-r")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"dot\nh\na\nba\ne\nc\n111 112\n121 122\n131 132\n" as &[u8],
+        ));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(dir.path())
+        .args(["-r", "--skip-hidden"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"a\nba\ne\nc\n" as &[u8]));
+
+    // Directories are not valid inputs on their own.
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i").arg(dir.path()).assert().failure();
+}
+
+#[cfg(unix)]
+#[test]
+fn arg_follow_links_works_as_expected() {
+    use std::os::unix::fs::symlink;
+
+    let dir = tempdir().unwrap();
+    let other = tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("sub")).unwrap();
+    std::fs::write(dir.path().join("a.txt"), "a\n").unwrap();
+    std::fs::write(dir.path().join("sub/b.txt"), "b\n").unwrap();
+    std::fs::write(other.path().join("c.txt"), "c\n").unwrap();
+    symlink(other.path(), dir.path().join("link")).unwrap();
+    // Links back to a directory being walked are not walked into again.
+    symlink(dir.path(), dir.path().join("sub/loop")).unwrap();
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(dir.path())
+        .arg("-r")
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"a\nb\n" as &[u8]));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(dir.path())
+        .args(["-r", "--follow-links"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"a\nc\nb\n" as &[u8]));
+}
+
+#[test]
+fn arg_skip_empty_works_as_expected() {
+    let files = testing_files!();
+    let empty = NamedTempFile::new().unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(empty.path())
+        .arg(files[1].path())
+        .args(["-p", "==\n", "--skip-empty"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"111 112\n121 122\n131 132\n==\n211 212\n221 222\n231 232\n" as &[u8],
+        ));
+}
+
+#[test]
+fn arg_append_works_as_expected() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("output.txt");
+    std::fs::write(&file_path, "a much longer previous output\n").unwrap();
+    let files = testing_files!();
+    for _ in 0..2 {
+        let mut cmd = Command::cargo_bin("fcc").unwrap();
+        cmd.arg("-i")
+            .arg(files[0].path())
+            .arg("-o")
+            .arg(&file_path)
+            .assert()
+            .success();
+    }
+    assert_eq!(
+        std::fs::read(&file_path).unwrap(),
+        b"111 112\n121 122\n131 132\n"
+    );
+
+    for _ in 0..2 {
+        let mut cmd = Command::cargo_bin("fcc").unwrap();
+        cmd.arg("-i")
+            .arg(files[1].path())
+            .arg("-o")
+            .arg(&file_path)
+            .arg("--append")
+            .assert()
+            .success();
+    }
+    assert_eq!(
+        std::fs::read(&file_path).unwrap(),
+        b"111 112\n121 122\n131 132\n211 212\n221 222\n231 232\n211 212\n221 222\n231 232\n"
+    );
+
+    // Only applies to an output file.
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg("--append")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn arg_repeat_works_as_expected() {
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .args(["--repeat", "3", "-S", "1", "-p", "==\n"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"111 112\n121 122\n131 132\n==\n121 122\n131 132\n==\n121 122\n131 132\n" as &[u8],
+        ));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .args(["--repeat", "0", "-p", "==\n"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+
+    // Inputs are checked once, before being repeated.
+    let dir = tempdir().unwrap();
+    let missing = dir.path().join("missing.csv");
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(&missing)
+        .arg(files[1].path())
+        .args(["--repeat", "2", "--keep-going"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"211 212\n221 222\n231 232\n211 212\n221 222\n231 232\n" as &[u8],
+        ))
+        .stderr(format!(
+            "fcc: skipping: the path provided at index 0 is not a valid file path: {}\n",
+            missing.display()
+        ));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(files[1].path())
+        .args(["--repeat", &usize::MAX.to_string()])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("too many inputs to repeat"));
+}
+
+#[test]
+fn arg_bom_works_as_expected() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    let mut file2 = NamedTempFile::new().unwrap();
+    file1.write_all(b"\xEF\xBB\xBFh1,h2\n11,12\n").unwrap();
+    file2.write_all(b"\xEF\xBB\xBFh1,h2\n21,22\n").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .args(["--bom", "-H"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"\xEF\xBB\xBFh1,h2\n11,12\n21,22\n" as &[u8]));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .arg("--strip-bom")
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"h1,h2\n11,12\nh1,h2\n21,22\n" as &[u8]));
+
+    // Appending only writes a byte order mark into an empty file.
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("output.csv");
+    for _ in 0..2 {
+        let mut cmd = Command::cargo_bin("fcc").unwrap();
+        cmd.arg("-i")
+            .arg(file1.path())
+            .args(["--bom", "-a", "-o"])
+            .arg(&file_path)
+            .assert()
+            .success();
+    }
+    assert_eq!(
+        std::fs::read(&file_path).unwrap(),
+        b"\xEF\xBB\xBFh1,h2\n11,12\nh1,h2\n11,12\n"
+    );
+}
+
+#[test]
+fn arg_detect_newline_style_works_as_expected() {
+    let mut lf = NamedTempFile::new().unwrap();
+    let mut crlf = NamedTempFile::new().unwrap();
+    let mut bare = NamedTempFile::new().unwrap();
+    write!(lf, "11\n12").unwrap();
+    write!(crlf, "21\r\n22").unwrap();
+    write!(bare, "31").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(lf.path())
+        .arg(crlf.path())
+        .arg(bare.path())
+        .args(["-n", "-N", "crlf", "--detect-newline-style"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"11\n12\n21\r\n22\r\n31\r\n" as &[u8]));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(lf.path())
+        .arg("--detect-newline-style")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn arg_keep_going_works_as_expected() {
+    let dir = tempdir().unwrap();
+    let missing = dir.path().join("missing.csv");
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(&missing)
+        .arg(files[1].path())
+        .arg("--keep-going")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"111 112\n121 122\n131 132\n211 212\n221 222\n231 232\n" as &[u8],
+        ))
+        .stderr(predicate::str::contains(missing.to_str().unwrap()));
+}
+
+#[test]
+fn arg_input_expands_response_files() {
+    let dir = tempdir().unwrap();
+    let files = testing_files!();
+    let nested = dir.path().join("nested.rsp");
+    let response = dir.path().join("inputs.rsp");
+    std::fs::write(&nested, files[2].path().to_str().unwrap()).unwrap();
+    std::fs::write(
+        &response,
+        format!(
+            "{}\n@{}\n",
+            files[1].path().to_str().unwrap(),
+            nested.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(format!("@{}", response.to_str().unwrap()))
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"111 112\n121 122\n131 132\n211 212\n221 222\n231 232\n311 312\n332 322\n331 332"
+                as &[u8],
+        ));
+
+    // A response file referencing itself is an error.
+    std::fs::write(&nested, format!("@{}", response.to_str().unwrap())).unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(format!("@{}", response.to_str().unwrap()))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("references itself"));
+}
+
+#[test]
+fn arg_limit_bytes_works_as_expected() {
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(files[1].path())
+        .arg(files[2].path())
+        .args(["--limit-bytes", "30"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"111 112\n121 122\n131 132\n211 21" as &[u8]));
+
+    // A limit beyond the output changes nothing.
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .args(["--limit-bytes", "1000"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"111 112\n121 122\n131 132\n" as &[u8]));
+
+    // Errors other than the limit itself are still reported once the limit has been reached.
+    let mut short = NamedTempFile::new().unwrap();
+    writeln!(short, "x").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(short.path())
+        .args(["-s", "1", "-e", "1", "--limit-bytes", "8"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("skip options given are not valid"));
+}
+
+#[test]
+fn arg_tee_works_as_expected() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("output.txt");
+    let files = testing_files!();
+    let merged = b"111 112\n121 122\n131 132\n211 212\n221 222\n231 232\n" as &[u8];
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(files[1].path())
+        .arg("-o")
+        .arg(&file_path)
+        .arg("--tee")
+        .assert()
+        .success()
+        .stdout(predicate::eq(merged));
+    assert_eq!(std::fs::read(&file_path).unwrap(), merged);
+}
+
+#[test]
+fn arg_start_with_and_end_with_work_as_expected() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    let mut file2 = NamedTempFile::new().unwrap();
+    write!(file1, "{{\"a\": 1}}").unwrap();
+    write!(file2, "{{\"b\": 2}}").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .args(["--start-with", "[", "--end-with", "]", "-p", ","])
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"[{\"a\": 1},{\"b\": 2}]" as &[u8]));
+
+    // Unlike paddings, they are written exactly once whatever the padding mode.
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .args([
+            "--start-with",
+            "[",
+            "--end-with",
+            "]",
+            "-p",
+            "|",
+            "-P",
+            "all",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"[|{\"a\": 1}|{\"b\": 2}|]" as &[u8]));
+
+    // Nor do the output transforms apply to them.
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .args(["--start-with", "[\n", "--end-with", "]\n"])
+        .args(["--number", "--trim-trailing-newline"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "[\n     1\t111 112\n     2\t121 122\n     3\t131 132]\n",
+        ));
+}
+
+#[test]
+fn arg_input_reads_stdin_from_dash() {
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg("-")
+        .arg(files[1].path())
+        .args(["-S", "1"])
+        .write_stdin("h\nfrom stdin\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"111 112\n121 122\n131 132\nfrom stdin\n221 222\n231 232\n" as &[u8],
+        ));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.args(["-i", "-", "-"])
+        .write_stdin("twice\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("only be given once"));
+}
+
+#[test]
+fn arg_input_dash_is_reported_as_dash() {
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg("-")
+        .arg(files[0].path())
+        .args(["--sort", "--dry-run"])
+        .write_stdin("from stdin\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::similar(format!(
+            "{}: 24 bytes, 3 lines\n-: 11 bytes, 1 lines\ntotal: 35 bytes\n",
+            files[0].path().display(),
+        )));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg("-")
+        .arg("--verbose")
+        .write_stdin("from stdin\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::similar(format!(
+            "fcc: {}: 24 bytes, 24 bytes written\nfcc: -: 11 bytes, 11 bytes written\n",
+            files[0].path().display(),
+        )));
+}
+
+#[test]
+fn arg_count_only_works_as_expected() {
+    let files = testing_files!();
+    let args = ["-S", "1", "-e", "1", "-p", "==\n"];
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    let merged = cmd
+        .arg("-i")
+        .arg(files[0].path())
+        .arg(files[1].path())
+        .arg(files[2].path())
+        .args(args)
+        .output()
+        .unwrap()
+        .stdout;
+    let lines = merged.iter().filter(|&&b| b == b'\n').count();
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(files[1].path())
+        .arg(files[2].path())
+        .args(args)
+        .arg("--count-only")
+        .assert()
+        .success()
+        .stdout(predicate::str::similar(format!(
+            "{} {}\n",
+            lines,
+            merged.len()
+        )));
+}
+
+#[test]
+fn arg_skip_head_once_keeps_a_multi_line_header_block() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    let mut file2 = NamedTempFile::new().unwrap();
+    let mut file3 = NamedTempFile::new().unwrap();
+    write!(file1, "# instrument\n# run 1\nh1,h2\n11,12\n").unwrap();
+    write!(file2, "# instrument\n# run 2\nh1,h2\n21,22\n").unwrap();
+    write!(file3, "# instrument\n# run 3\n").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .args(["-S", "3"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"# instrument\n# run 1\nh1,h2\n11,12\n21,22\n" as &[u8],
+        ));
+
+    // A source shorter than the header block fails, unless skips are lenient.
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file3.path())
+        .arg(file2.path())
+        .args(["-S", "3"])
+        .assert()
+        .failure();
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file3.path())
+        .arg(file2.path())
+        .args(["-S", "3", "--lenient-skip"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"# instrument\n# run 1\nh1,h2\n11,12\n21,22\n" as &[u8],
+        ));
+}
+
+#[test]
+fn explicit_stdin_path_separators_work_as_expected() {
+    let dir = tempdir().unwrap();
+    let spaced = dir.path().join("settings (2).txt");
+    std::fs::write(&spaced, "spaced\n").unwrap();
+    let files = testing_files!();
+    let plain = files[0].path().to_str().unwrap();
+    let spaced = spaced.to_str().unwrap();
+    let merged = b"111 112\n121 122\n131 132\nspaced\n" as &[u8];
+
+    // A single line is taken as a single path.
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.write_stdin(format!("{}\n", spaced))
+        .arg("--paths-newline")
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"spaced\n" as &[u8]));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.write_stdin(format!("{}\n{}\n", plain, spaced))
+        .arg("--paths-newline")
+        .assert()
+        .success()
+        .stdout(predicate::eq(merged));
+
+    // Which is the default.
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.write_stdin(format!("{}\n", spaced))
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"spaced\n" as &[u8]));
+
+    // Newlines are whitespace too.
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.write_stdin(format!("{}\n'{}'\n", plain, spaced))
+        .arg("--paths-space")
+        .assert()
+        .success()
+        .stdout(predicate::eq(merged));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.write_stdin(format!("{}\0{}", plain, spaced))
+        .arg("--paths-null")
+        .assert()
+        .success()
+        .stdout(predicate::eq(merged));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.write_stdin(plain)
+        .args(["--paths-newline", "--paths-space"])
+        .assert()
+        .failure();
+}
+
+#[cfg(feature = "encoding")]
+#[test]
+fn arg_transcode_utf8_works_as_expected() {
+    let mut utf16 = NamedTempFile::new().unwrap();
+    let mut utf8 = NamedTempFile::new().unwrap();
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend(
+        "h1,h2\nné,12\n"
+            .encode_utf16()
+            .flat_map(|u| u.to_le_bytes()),
+    );
+    utf16.write_all(&bytes).unwrap();
+    write!(utf8, "h1,h2\nça,22\n").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(utf16.path())
+        .arg(utf8.path())
+        .args(["--transcode-utf8", "-H"])
+        .assert()
+        .success()
+        .stdout(predicate::eq("h1,h2\nné,12\nça,22\n".as_bytes()));
+}
+
+#[cfg(feature = "encoding")]
+#[test]
+fn arg_transcode_utf8_reports_original_paths() {
+    let mut utf16 = NamedTempFile::new().unwrap();
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend("a\n".encode_utf16().flat_map(|u| u.to_le_bytes()));
+    utf16.write_all(&bytes).unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(utf16.path())
+        .args(["--transcode-utf8", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::similar(format!(
+            "{}: 2 bytes, 1 lines\ntotal: 2 bytes\n",
+            utf16.path().display()
+        )));
+
+    // Sources are reported with their size before being transcoded.
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(utf16.path())
+        .args(["--transcode-utf8", "-vv"])
+        .assert()
+        .success()
+        .stdout(predicate::eq("a\n"))
+        .stderr(format!(
+            "fcc: {0}: 6 bytes, 2 bytes written\nfcc: {0}: 0 bytes dropped up front, 0 bytes skipped\n",
+            utf16.path().display()
+        ));
+}
+
+#[test]
+fn arg_skip_blank_lines_works_as_expected() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    let mut file2 = NamedTempFile::new().unwrap();
+    write!(file1, "h1,h2\n\n11,12\n \t\r\n12,13\n\n").unwrap();
+    write!(file2, "h1,h2\n\r\n21,22\n  ").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .args(["--skip-blank-lines", "-H", "-p", "\n"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"h1,h2\n11,12\n12,13\n\n21,22\n" as &[u8]));
+}
+
+#[test]
+fn arg_pad_distinct_works_as_expected() {
+    let files = testing_files!();
+    let mut copy = NamedTempFile::new().unwrap();
+    write!(copy, "111 112\n121 122\n131 132\n").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(copy.path())
+        .arg(files[1].path())
+        .args(["-p", "==\n", "--pad-distinct"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"111 112\n121 122\n131 132\n111 112\n121 122\n131 132\n==\n211 212\n221 222\n231 232\n"
+                as &[u8],
+        ));
+}
+
+#[test]
+fn arg_newline_between_works_as_expected() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    let mut file2 = NamedTempFile::new().unwrap();
+    let mut file3 = NamedTempFile::new().unwrap();
+    write!(file1, "11\n12\n").unwrap();
+    write!(file2, "21").unwrap();
+    write!(file3, "31\n32\n").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .arg(file3.path())
+        .args(["--newline-between", "-N", "crlf"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"11\n12\n\r\n21\r\n31\n32\n" as &[u8]));
+
+    for layout in [["--interleave", "1"], ["--columns", ","]] {
+        let mut cmd = Command::cargo_bin("fcc").unwrap();
+        cmd.arg("-i")
+            .arg(file1.path())
+            .arg(file2.path())
+            .arg("--newline-between")
+            .args(layout)
+            .assert()
+            .failure();
+    }
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn arg_jsonl_works_as_expected() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    let mut file2 = NamedTempFile::new().unwrap();
+    write!(file1, "{{\"a\":1}}\n{{\"a\":\n\n").unwrap();
+    write!(file2, "{{\"a\":2}}\n[3]").unwrap();
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .args(["--jsonl", "pass", "-n"])
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":1}\n{\"a\":\n\n{\"a\":2}\n[3]\n"));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .args(["--jsonl", "drop-invalid", "-n"])
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":1}\n\n{\"a\":2}\n[3]\n"))
+        .stderr(predicate::str::contains("line 2: invalid JSON"));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .args(["--jsonl", "fail-fast"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("line 2: invalid JSON"));
+}
+
+#[test]
+fn arg_pad_file_works_as_expected() {
+    let files = testing_files!();
+    let mut banner = NamedTempFile::new().unwrap();
+    write!(banner, "+--+\n|  |\n+--+\n").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(files[1].path())
+        .arg("--pad-file")
+        .arg(banner.path())
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"111 112\n121 122\n131 132\n+--+\n|  |\n+--+\n211 212\n221 222\n231 232\n" as &[u8],
+        ));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(files[1].path())
+        .arg("--pad-file")
+        .arg(banner.path())
+        .args(["-p", "==\n"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn arg_number_works_as_expected() {
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(files[1].path())
+        .args(["--number", "--skip-head", "1", "-p", "==\n"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "     1\t121 122\n     2\t131 132\n     3\t==\n     4\t221 222\n     5\t231 232\n",
+        ));
+}
+
+#[test]
+fn arg_number_format_works_as_expected() {
+    let files = testing_files!();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .args(["--number", "--number-format", "{:03}: ", "--skip-head", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::eq("001: 121 122\n002: 131 132\n"));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .args([
+            "--number",
+            "--number-format",
+            "[{:<3}] ",
+            "--skip-head",
+            "2",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::eq("[1  ] 131 132\n"));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .args(["--number", "--number-format", "no placeholder"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("placeholder"));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .args(["--number", "--number-format", "{:70000}"])
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("width 70000 is larger than 65535"));
+}
+
+#[test]
+fn arg_squeeze_blank_works_as_expected() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    let mut file2 = NamedTempFile::new().unwrap();
+    write!(file1, "11\n\n\n12\n \n\n\n").unwrap();
+    write!(file2, "\r\n\n21\n\n").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .args(["--squeeze-blank", "--number"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "     1\t11\n     2\t\n     3\t12\n     4\t \n     5\t\n     6\t21\n     7\t\n",
+        ));
+}
+
+#[test]
+fn arg_show_ends_and_show_tabs_work_as_expected() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    let mut file2 = NamedTempFile::new().unwrap();
+    write!(file1, "h1\th2\n11\t12\n").unwrap();
+    write!(file2, "h1\th2\n21\t22 ").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .args(["--show-ends", "-H", "-n"])
+        .assert()
+        .success()
+        .stdout(predicate::eq("h1\th2$\n11\t12$\n21\t22 $\n"));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .args(["--show-tabs", "--show-ends", "--number", "-H"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "     1\th1^Ih2$\n     2\t11^I12$\n     3\t21^I22 ",
+        ));
+
+    // Carriage returns are made visible along with line ends.
+    let mut crlf = NamedTempFile::new().unwrap();
+    write!(crlf, "a\tb\r\nc\r\n").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(crlf.path())
+        .args(["--show-ends", "--show-tabs"])
+        .assert()
+        .success()
+        .stdout(predicate::eq("a^Ib^M$\nc^M$\n"));
+}
+
+#[test]
+fn skips_handle_sources_made_of_newlines() {
+    for (contents, head, tail) in [
+        ("\n", "", ""),
+        ("\n\n", "\n\n", "\n\n"),
+        ("x\n", "", ""),
+        ("x\n\n", "\n\n", "x\nx\n"),
+    ] {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", contents).unwrap();
+        for (skip, expected) in [("--skip-head", head), ("--skip-tail", tail)] {
+            let mut cmd = Command::cargo_bin("fcc").unwrap();
+            cmd.arg("-i")
+                .arg(file.path())
+                .arg(file.path())
+                .args([skip, "1"])
+                .assert()
+                .success()
+                .stdout(predicate::eq(expected));
+        }
+    }
+}
+
+#[test]
+fn arg_newline_looks_at_the_bytes_left_after_skips() {
+    let mut file1 = NamedTempFile::new().unwrap();
+    let mut file2 = NamedTempFile::new().unwrap();
+    write!(file1, "h1,h2\n11,12\n").unwrap();
+    write!(file2, "h1,h2\n21,22").unwrap();
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .args(["-n", "--skip-mode", "bytes", "--skip-tail", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::eq("h1,h2\n11,12\nh1,h2\n21,2\n"));
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file1.path())
+        .arg(file2.path())
+        .args(["-n", "--skip-tail", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::eq("h1,h2\nh1,h2\n"));
+
+    // Sources skipped entirely get no newline.
+    let mut file3 = NamedTempFile::new().unwrap();
+    let mut file4 = NamedTempFile::new().unwrap();
+    write!(file3, "x").unwrap();
+    write!(file4, "y").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file3.path())
+        .arg(file4.path())
+        .args(["-n", "-s", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+}
+
+#[test]
+fn arg_error_on_empty_works_as_expected() {
+    let files = testing_files!();
+    let dir = tempdir().unwrap();
+    let output = dir.path().join("merged.txt");
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(files[1].path())
+        .args(["--skip-head", "3", "--error-on-empty", "-o"])
+        .arg(&output)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("contributed nothing"));
+    assert!(!output.exists());
+
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(files[0].path())
+        .arg(files[1].path())
+        .args(["--skip-head", "2", "--error-on-empty"])
+        .assert()
+        .success()
+        .stdout(predicate::eq("131 132\n231 232\n"));
+
+    // What gets written around the merge does not count.
+    for around in [
+        &["--bom"][..],
+        &["--start-with", "[", "--end-with", "]"][..],
+    ] {
+        let mut cmd = Command::cargo_bin("fcc").unwrap();
+        cmd.arg("-i")
+            .arg(files[0].path())
+            .arg(files[1].path())
+            .args(["--skip-head", "3", "--error-on-empty"])
+            .args(around)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("contributed nothing"));
+    }
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn arg_jsonl_reports_original_paths() {
+    let mut file = NamedTempFile::new().unwrap();
+    write!(file, "{{\"a\":1}}\n{{\"a\":\n").unwrap();
+    let mut cmd = Command::cargo_bin("fcc").unwrap();
+    cmd.arg("-i")
+        .arg(file.path())
+        .arg("-")
+        .args(["--jsonl", "drop-invalid", "--verbose"])
+        .write_stdin("[1\n[2]\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":1}\n[2]\n"))
+        .stderr(predicate::str::starts_with(format!(
+            "fcc: dropping: {}: line 2: invalid JSON",
+            file.path().display()
+        )))
+        .stderr(predicate::str::contains(
+            "fcc: dropping: -: line 1: invalid JSON",
+        ))
+        .stderr(predicate::str::contains(format!(
+            "fcc: {}: 14 bytes, 8 bytes written\nfcc: -: 7 bytes, 4 bytes written\n",
+            file.path().display()
+        )));
+}